@@ -0,0 +1,224 @@
+use std::collections::HashSet;
+
+use crate::reminder::Reminder;
+
+/// Marks a line as a new reminder in place of an id.
+const NEW: &str = "-";
+
+/// A parsed line of the document `rmnd edit-all` opens, `id` is `None` for new reminders.
+pub(crate) struct Entry {
+    pub(crate) id: Option<String>,
+    pub(crate) priority: String,
+    pub(crate) text: String,
+}
+
+/// What applying the edited document changes, for the summary printed afterwards.
+#[derive(Default, PartialEq)]
+pub(crate) struct Summary {
+    pub(crate) added: usize,
+    pub(crate) removed: usize,
+    pub(crate) changed: usize,
+    pub(crate) reordered: bool,
+}
+
+fn quote(priority: &str) -> String {
+    if priority.is_empty() || priority.contains(char::is_whitespace) || priority.starts_with('"') {
+        format!("\"{}\"", priority.replace('"', ""))
+    } else {
+        priority.to_string()
+    }
+}
+
+/// Keeps a text on one line, newlines are written as `\n` and backslashes doubled.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('\n', "\\n").replace('\r', "\\r")
+}
+
+/// Reverses `escape`, a backslash before any other character is kept.
+fn unescape(text: &str) -> String {
+    let mut result = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('r') => result.push('\r'),
+            Some('\\') => result.push('\\'),
+            Some(other) => {
+                result.push('\\');
+                result.push(other);
+            },
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+/// Renders reminders one per line as `id priority text`, below a comment explaining the format.
+pub(crate) fn render(context: &str, reminders: &[Reminder]) -> String {
+    let mut document = format!(
+        "# Reminders of {}\n\
+         #\n\
+         # One reminder per line: <id> <priority> <text>, quote priorities containing spaces. Write a line break in a\n\
+         # text as \\n and a backslash as \\\\.\n\
+         # Edit the priority or text, reorder lines to reorder the reminders and delete a line to remove its reminder.\n\
+         # Add a reminder with {} as its id. Other fields of existing reminders are kept.\n\
+         # Lines starting with # and empty lines are ignored, nothing is saved if any line is invalid.\n\n",
+        context, NEW
+    );
+    for reminder in reminders {
+        document.push_str(&format!("{} {} {}\n", reminder.id, quote(&reminder.priority), escape(&reminder.text)));
+    }
+    document
+}
+
+/// Parses an edited document. `ids` are those of the reminders it was rendered from and `priorities` the names that
+/// may be used, every invalid line is reported with its line number.
+pub(crate) fn parse(document: &str, ids: &[String], priorities: &[String]) -> Result<Vec<Entry>, Vec<String>> {
+    let mut entries = vec![];
+    let mut errors = vec![];
+    let mut seen = HashSet::new();
+
+    for (index, line) in document.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let number = index + 1;
+
+        let (id, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim_start();
+        let (priority, text) = if let Some(quoted) = rest.strip_prefix('"') {
+            match quoted.split_once('"') {
+                Some((priority, text)) => (priority, text),
+                None => {
+                    errors.push(format!("line {}: unterminated quote around the priority", number));
+                    continue;
+                },
+            }
+        } else {
+            rest.split_once(char::is_whitespace).unwrap_or((rest, ""))
+        };
+        let text = text.trim();
+
+        if priority.is_empty() || text.is_empty() {
+            errors.push(format!("line {}: expected <id> <priority> <text>", number));
+            continue;
+        }
+
+        let priority = match priorities.iter().find(|v| *v == priority).or_else(|| priorities.iter().find(|v| v.eq_ignore_ascii_case(priority))) {
+            Some(priority) => priority.clone(),
+            None => {
+                errors.push(format!("line {}: unknown priority {}, expected one of {}", number, priority, priorities.join(", ")));
+                continue;
+            },
+        };
+
+        let id = if id == NEW {
+            None
+        } else if !ids.iter().any(|v| v == id) {
+            errors.push(format!("line {}: unknown id {}, use {} for new reminders", number, id, NEW));
+            continue;
+        } else if !seen.insert(id.to_string()) {
+            errors.push(format!("line {}: id {} is used more than once", number, id));
+            continue;
+        } else {
+            Some(id.to_string())
+        };
+
+        entries.push(Entry { id, priority, text: unescape(text) });
+    }
+
+    if errors.is_empty() {
+        Ok(entries)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Builds the new list of reminders from `entries`, existing reminders keep their other fields and new ones are
+/// written by `author`.
+pub(crate) fn apply(original: &[Reminder], entries: Vec<Entry>, author: &str) -> (Vec<Reminder>, Summary) {
+    let mut summary = Summary::default();
+    let mut reminders = vec![];
+    let mut kept = vec![];
+
+    for entry in entries {
+        match entry.id.and_then(|id| original.iter().position(|v| v.id == id)) {
+            Some(index) => {
+                let mut reminder = original[index].clone();
+                if reminder.priority != entry.priority || reminder.text != entry.text {
+                    summary.changed += 1;
+                }
+                reminder.priority = entry.priority;
+                reminder.text = entry.text;
+                reminders.push(reminder);
+                kept.push(index);
+            },
+            None => {
+                summary.added += 1;
+                reminders.push(Reminder { priority: entry.priority, author: author.to_string(), text: entry.text, ..Default::default() });
+            },
+        }
+    }
+
+    summary.removed = original.len() - kept.len();
+    summary.reordered = kept.windows(2).any(|v| v[0] > v[1]);
+    (reminders, summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reminder(id: &str, priority: &str, text: &str) -> Reminder {
+        Reminder { id: id.to_string(), priority: priority.to_string(), text: text.to_string(), ..Default::default() }
+    }
+
+    #[test]
+    fn round_trip() {
+        let reminders = [
+            reminder("a", "Low", "Call mom\nthen dad"),
+            reminder("b", "Very high", "C:\\new\\folder and a literal \\n"),
+            reminder("c", "Low", "trailing backslash \\"),
+        ];
+        let document = render("ctx", &reminders);
+        assert_eq!(document.lines().filter(|v| !v.starts_with('#') && !v.is_empty()).count(), 3);
+
+        let ids: Vec<String> = reminders.iter().map(|v| v.id.clone()).collect();
+        let priorities = ["Low".to_string(), "Very high".to_string()];
+        let entries = parse(&document, &ids, &priorities).ok().unwrap();
+        for (entry, reminder) in entries.iter().zip(&reminders) {
+            assert_eq!(entry.id.as_deref(), Some(reminder.id.as_str()));
+            assert_eq!(entry.priority, reminder.priority);
+            assert_eq!(entry.text, reminder.text);
+        }
+    }
+
+    #[test]
+    fn invalid_lines_are_reported() {
+        let ids = ["a".to_string()];
+        let priorities = ["Low".to_string()];
+        let errors = parse("a Low one\na Low twice\nz Low unknown\n- Nope text\n- \"Low\n- Low\n", &ids, &priorities).err().unwrap();
+        assert_eq!(errors.len(), 5);
+        assert!(errors[0].starts_with("line 2: id a"));
+        assert!(errors[4].starts_with("line 6: expected"));
+    }
+
+    #[test]
+    fn apply_summarizes() {
+        let original = [reminder("a", "Low", "one"), reminder("b", "Low", "two"), reminder("c", "Low", "three")];
+        let entries = vec![
+            Entry { id: Some("b".to_string()), priority: "Low".to_string(), text: "two".to_string() },
+            Entry { id: Some("a".to_string()), priority: "Low".to_string(), text: "one!".to_string() },
+            Entry { id: None, priority: "Low".to_string(), text: "four".to_string() },
+        ];
+        let (reminders, summary) = apply(&original, entries, "me");
+        assert_eq!(reminders.iter().map(|v| v.text.as_str()).collect::<Vec<_>>(), ["two", "one!", "four"]);
+        assert_eq!(reminders[2].author, "me");
+        assert!(summary == Summary { added: 1, removed: 1, changed: 1, reordered: true });
+    }
+}
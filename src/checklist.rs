@@ -0,0 +1,65 @@
+use chrono::{DateTime, Local, SubsecRound};
+use serde_derive::{Deserialize, Serialize};
+
+/// An ordered list of steps, e.g. a release procedure, walked through with `rmnd checklist run`.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub(crate) struct Checklist {
+    pub(crate) name: String,
+    pub(crate) steps: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) runs: Vec<ChecklistRun>,
+}
+
+/// One walk through a checklist, a run without `finished_at` is resumed by the next `rmnd checklist run`.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub(crate) struct ChecklistRun {
+    pub(crate) started_by: String,
+    pub(crate) started_at: DateTime<Local>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) finished_at: Option<DateTime<Local>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) checked: Vec<CheckedStep>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub(crate) struct CheckedStep {
+    pub(crate) step: String,
+    /// The position of the step, steps may repeat. Runs recorded before this was kept match by text.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) index: Option<usize>,
+    pub(crate) by: String,
+    pub(crate) at: DateTime<Local>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) skipped: bool,
+}
+
+impl Checklist {
+    /// The unfinished run, if any.
+    pub(crate) fn current_run(&mut self) -> Option<&mut ChecklistRun> {
+        self.runs.last_mut().filter(|v| v.finished_at.is_none())
+    }
+}
+
+impl ChecklistRun {
+    pub(crate) fn new(started_by: String) -> Self {
+        Self {
+            started_by,
+            started_at: now(),
+            finished_at: None,
+            checked: vec![],
+        }
+    }
+
+    /// How the step at `index`, with the text `step`, was checked in this run.
+    pub(crate) fn checked(&self, index: usize, step: &str) -> Option<&CheckedStep> {
+        self.checked.iter().find(|v| match v.index {
+            Some(checked) => checked == index,
+            None => v.step == step,
+        })
+    }
+}
+
+/// The current time to the second, sub-second precision only adds noise to the config file.
+pub(crate) fn now() -> DateTime<Local> {
+    Local::now().trunc_subsecs(0)
+}
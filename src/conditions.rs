@@ -0,0 +1,226 @@
+use std::{
+    path::Path,
+    process::{Command, Stdio},
+    str::FromStr,
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use chrono::{DateTime, Local, NaiveDateTime, NaiveTime, TimeZone, Timelike};
+use regex::Regex;
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A predicate a reminder's `when` list is made of, all of them must hold for the reminder to be shown. Conditions
+/// are stored as strings in a small language, any of them can be negated with a leading `!`:
+///
+/// - `env:NAME` the environment variable is set and not empty
+/// - `env:NAME=VALUE` the environment variable equals `VALUE`
+/// - `env:NAME~REGEX` the environment variable matches `REGEX`
+/// - `exists:PATH` the file or directory exists, relative to the context
+/// - `newer:PATH:AGE` the file was modified within `AGE`, e.g. `30m`, `12h`, `7d` or `2w`
+/// - `cmd[=STATUS][@TIMEOUT]:COMMAND` the shell command exits with `STATUS` (0 by default) within `TIMEOUT` (5s by
+///   default), run in the context's directory
+pub(crate) struct Condition {
+    negated: bool,
+    test: Test,
+}
+
+enum Test {
+    EnvSet(String),
+    EnvEquals(String, String),
+    EnvMatches(String, Regex),
+    Exists(String),
+    Newer(String, Duration),
+    Command {
+        command: String,
+        status: i32,
+        timeout: Duration,
+    },
+}
+
+/// The result of evaluating a condition, with a description of what was found for `show --explain`.
+pub(crate) struct Outcome {
+    pub(crate) holds: bool,
+    pub(crate) detail: String,
+}
+
+/// Parses an amount with an optional unit, e.g. `30m`, `12h`, `7d` or `2w`, seconds by default.
+pub(crate) fn parse_duration(text: &str) -> Result<Duration, String> {
+    let split = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    let (amount, unit) = text.split_at(split);
+    let amount: u64 = amount.parse().map_err(|_| format!("Invalid duration {}.", text))?;
+    let seconds = match unit {
+        "s" | "" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(format!("Invalid duration unit in {}, expected s, m, h, d or w.", text)),
+    };
+    Ok(Duration::from_secs(amount * seconds))
+}
+
+/// Parses the time given to `--remind-at`: `YYYY-MM-DD HH:MM`, `HH:MM` for the next time the clock shows it, or a
+/// duration from now such as `30m`.
+pub(crate) fn parse_time(text: &str) -> Result<DateTime<Local>, String> {
+    let text = text.trim();
+    let now = Local::now();
+    let local = |naive: NaiveDateTime| Local.from_local_datetime(&naive).earliest().ok_or_else(|| format!("{} does not exist in the local time zone.", text));
+
+    for format in ["%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S"] {
+        if let Ok(naive) = NaiveDateTime::parse_from_str(text, format) {
+            return local(naive);
+        }
+    }
+    if let Ok(time) = NaiveTime::parse_from_str(text, "%H:%M") {
+        let today = local(now.date_naive().and_time(time))?;
+        return if today > now { Ok(today) } else { local(now.date_naive().succ_opt().unwrap().and_time(time)) };
+    }
+    match parse_duration(text) {
+        Ok(duration) => Ok((now + chrono::Duration::from_std(duration).map_err(|v| v.to_string())?).with_nanosecond(0).unwrap()),
+        Err(_) => Err(format!("Invalid time {}, expected \"YYYY-MM-DD HH:MM\", HH:MM or a duration such as 30m.", text)),
+    }
+}
+
+impl FromStr for Condition {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let (negated, text) = match text.trim().strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, text.trim()),
+        };
+
+        let Some((kind, argument)) = text.split_once(':') else {
+            return Err(format!("Invalid condition {}, expected kind:argument.", text));
+        };
+
+        let test = match kind {
+            "env" => {
+                if let Some((name, value)) = argument.split_once('=') {
+                    Test::EnvEquals(name.to_string(), value.to_string())
+                } else if let Some((name, pattern)) = argument.split_once('~') {
+                    let regex = Regex::new(pattern).map_err(|e| format!("Invalid regex {}: {}", pattern, e))?;
+                    Test::EnvMatches(name.to_string(), regex)
+                } else {
+                    Test::EnvSet(argument.to_string())
+                }
+            },
+            "exists" => Test::Exists(argument.to_string()),
+            "newer" => {
+                let Some((path, age)) = argument.rsplit_once(':') else {
+                    return Err(format!("Invalid condition {}, expected newer:PATH:AGE.", text));
+                };
+                Test::Newer(path.to_string(), parse_duration(age)?)
+            },
+            _ if kind.starts_with("cmd") => {
+                let options = &kind[3..];
+                let (status, timeout) = match options.split_once('@') {
+                    Some((status, timeout)) => (status, Some(timeout)),
+                    None => (options, None),
+                };
+                let status = match status.strip_prefix('=') {
+                    Some(status) => status.parse().map_err(|_| format!("Invalid exit status in {}.", text))?,
+                    None if status.is_empty() => 0,
+                    None => return Err(format!("Invalid condition {}, expected cmd[=STATUS][@TIMEOUT]:COMMAND.", text)),
+                };
+                let timeout = match timeout {
+                    Some(timeout) => parse_duration(timeout)?,
+                    None => DEFAULT_TIMEOUT,
+                };
+                Test::Command {
+                    command: argument.to_string(),
+                    status,
+                    timeout,
+                }
+            },
+            _ => return Err(format!("Unknown condition kind {}, expected env, exists, newer or cmd.", kind)),
+        };
+
+        Ok(Self { negated, test })
+    }
+}
+
+impl Condition {
+    /// Evaluates the condition, relative paths and commands are resolved against `dir`.
+    pub(crate) fn evaluate(&self, dir: &Path) -> Outcome {
+        let (holds, detail) = match &self.test {
+            Test::EnvSet(name) => match std::env::var(name) {
+                Ok(value) if !value.is_empty() => (true, format!("{} is set", name)),
+                _ => (false, format!("{} is not set", name)),
+            },
+            Test::EnvEquals(name, expected) => match std::env::var(name) {
+                Ok(value) => (value == *expected, format!("{} is {}", name, value)),
+                Err(_) => (false, format!("{} is not set", name)),
+            },
+            Test::EnvMatches(name, regex) => match std::env::var(name) {
+                Ok(value) => (regex.is_match(&value), format!("{} is {}", name, value)),
+                Err(_) => (false, format!("{} is not set", name)),
+            },
+            Test::Exists(path) => {
+                let exists = dir.join(path).exists();
+                (exists, format!("{} {}", path, if exists { "exists" } else { "does not exist" }))
+            },
+            Test::Newer(path, age) => {
+                match dir.join(path).metadata().and_then(|v| v.modified()) {
+                    Ok(modified) => {
+                        let elapsed = SystemTime::now().duration_since(modified).unwrap_or_default();
+                        (elapsed <= *age, format!("{} was modified {} ago", path, format_duration(elapsed)))
+                    },
+                    Err(_) => (false, format!("{} does not exist", path)),
+                }
+            },
+            Test::Command {
+                command,
+                status,
+                timeout,
+            } => match run(command, dir, *timeout) {
+                Ok(code) => (code == *status, format!("`{}` exited with {}", command, code)),
+                Err(error) => (false, error),
+            },
+        };
+
+        Outcome {
+            holds: holds != self.negated,
+            detail,
+        }
+    }
+}
+
+/// Runs a shell command, returning its exit status or why it did not finish in time.
+fn run(command: &str, dir: &Path, timeout: Duration) -> Result<i32, String> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("`{}` could not be run: {}", command, e))?;
+
+    let start = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => return Ok(status.code().unwrap_or(-1)),
+            Ok(None) if start.elapsed() >= timeout => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("`{}` timed out after {}", command, format_duration(timeout)));
+            },
+            Ok(None) => thread::sleep(Duration::from_millis(10)),
+            Err(error) => return Err(format!("`{}` could not be waited on: {}", command, error)),
+        }
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match seconds {
+        0..=59 => format!("{}s", seconds),
+        60..=3599 => format!("{}m", seconds / 60),
+        3600..=86399 => format!("{}h", seconds / 3600),
+        _ => format!("{}d", seconds / 86400),
+    }
+}
@@ -1,81 +1,318 @@
-use std::path::PathBuf;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
 
-use crate::reminder::{Color, LocalReminder, Priority, Reminder};
+use crate::{
+    checklist::Checklist,
+    reminder::{self, Color, LocalPriority, LocalReminder, Priority, Reminder},
+};
 // use serde::{Serialize, Deserialize};
 use serde_derive::{Deserialize, Serialize};
 
 pub(crate) struct ConfigSum {
-    pub(crate) priorities: Vec<Priority>,
+    pub(crate) global_path: PathBuf,
+    /// Paths of the local contexts included in this sum, whether or not they hold any reminders.
+    pub(crate) contexts: Vec<PathBuf>,
+    /// Virtual paths of the pathless contexts included in this sum, see `PathlessContext`.
+    pub(crate) pathless: Vec<PathBuf>,
+    pub(crate) priorities: Vec<LocalPriority>,
     pub(crate) reminders: Vec<LocalReminder>,
 }
 
-#[derive(Serialize, Deserialize)]
+/// How a config file is stored, chosen by its extension. The global config is always toml.
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum Format {
+    Toml,
+    Yaml,
+    Json,
+    Json5,
+}
+
+/// Why a config file could not be parsed, `line` counts from 1.
+pub(crate) struct ParseError {
+    pub(crate) message: String,
+    pub(crate) line: Option<usize>,
+}
+
+impl Format {
+    /// The names `rmnd init --format` and `rmnd convert --to` take, which are also the extensions written.
+    pub(crate) const NAMES: [&'static str; 4] = ["toml", "yaml", "json", "json5"];
+
+    /// The format of the file at `path`, toml unless its extension names another one.
+    pub(crate) fn of(path: &Path) -> Self {
+        match path.extension().and_then(|v| v.to_str()) {
+            Some("yaml" | "yml") => Format::Yaml,
+            Some("json") => Format::Json,
+            Some("json5") => Format::Json5,
+            _ => Format::Toml,
+        }
+    }
+
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        Self::NAMES.iter().position(|v| *v == name).map(|v| [Format::Toml, Format::Yaml, Format::Json, Format::Json5][v])
+    }
+
+    pub(crate) fn extension(self) -> &'static str {
+        match self {
+            Format::Toml => "toml",
+            Format::Yaml => "yaml",
+            Format::Json => "json",
+            Format::Json5 => "json5",
+        }
+    }
+
+    pub(crate) fn parse(self, bytes: &[u8]) -> Result<ConfigFile, ParseError> {
+        match self {
+            Format::Toml => toml::from_slice(bytes).map_err(|e| ParseError { line: e.line_col().map(|v| v.0 + 1), message: e.to_string() }),
+            Format::Yaml => serde_yaml::from_slice(bytes).map_err(|e| ParseError { line: e.location().map(|v| v.line()), message: e.to_string() }),
+            Format::Json => serde_json::from_slice(bytes).map_err(|e| ParseError { line: Some(e.line()).filter(|v| *v > 0), message: e.to_string() }),
+            Format::Json5 => {
+                let text = std::str::from_utf8(bytes).map_err(|e| ParseError { line: None, message: e.to_string() })?;
+                json5::from_str(text).map_err(|e| {
+                    let json5::Error::Message { msg, location } = e;
+                    ParseError { line: location.map(|v| v.line), message: msg }
+                })
+            },
+        }
+    }
+
+    /// Writes `config` to replace `existing`, keeping the comments and layout of a toml file so that only the entries
+    /// that changed are rewritten.
+    pub(crate) fn write_over(self, config: &ConfigFile, existing: Option<&str>) -> String {
+        let output = self.write(config);
+        match existing {
+            Some(existing) if self == Format::Toml => crate::layout::update(existing, &output).unwrap_or(output),
+            _ => output,
+        }
+    }
+
+    /// Writes `config`, JSON5 files are written as plain JSON which is also valid JSON5.
+    pub(crate) fn write(self, config: &ConfigFile) -> String {
+        match self {
+            Format::Toml => toml::to_string_pretty(config).unwrap(),
+            Format::Yaml => serde_yaml::to_string(config).unwrap(),
+            Format::Json | Format::Json5 => serde_json::to_string_pretty(config).unwrap() + "\n",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
 pub(crate) struct ConfigFile {
+    // Only the global config lists contexts, local files written by hand may leave these out.
+    #[serde(default)]
     pub(crate) config_paths: Vec<String>,
-    //
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) contexts: Vec<PathlessContext>,
+    // Empty arrays are skipped, toml cannot write a plain value after an array of tables.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub(crate) priorities: Vec<Priority>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub(crate) reminders: Vec<Reminder>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) checklists: Vec<Checklist>,
     //
+    #[serde(default)]
     pub(crate) settings: Settings,
     //
     #[serde(skip_serializing)]
     pub(crate) path: Option<PathBuf>,
+    /// Set when this config was loaded out of a `PathlessContext`, saving writes it back into the global config.
+    #[serde(skip)]
+    pub(crate) pathless: bool,
+    /// The line each reminder is defined on, see `ConfigFile::read_lines`.
+    #[serde(skip)]
+    pub(crate) lines: Vec<usize>,
 }
 
-#[derive(Serialize, Deserialize)]
+/// A context stored inside the global config, keyed by its directory, for when a rmnd.toml in the directory itself is
+/// not wanted (`rmnd init --no-file`). Its reminders are addressed by the virtual path `directory/rmnd.toml`.
+#[derive(Serialize, Deserialize, Clone)]
+pub(crate) struct PathlessContext {
+    pub(crate) directory: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) priorities: Vec<Priority>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) reminders: Vec<Reminder>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) checklists: Vec<Checklist>,
+    /// The line each reminder is defined on in the global config.
+    #[serde(skip)]
+    pub(crate) lines: Vec<usize>,
+}
+
+/// The parts of a config file whose position is tracked. toml only reports spans for plain values, so a reminder is
+/// located by its `text`.
+#[derive(Deserialize)]
+struct Spans {
+    #[serde(default)]
+    contexts: Vec<ReminderSpans>,
+    #[serde(default)]
+    reminders: Vec<TextSpan>,
+}
+
+#[derive(Deserialize)]
+struct ReminderSpans {
+    #[serde(default)]
+    reminders: Vec<TextSpan>,
+}
+
+#[derive(Deserialize)]
+struct TextSpan {
+    text: toml::Spanned<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
 pub(crate) struct Settings {
     pub(crate) name: Option<String>,
     pub(crate) username: Option<String>,
     pub(crate) email: Option<String>,
+    /// Comment markers `rmnd scan` looks for, defaults to `scan::DEFAULT_MARKERS`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) markers: Option<Vec<String>>,
+    /// Maps marker tags or markers, e.g. `high` in `REMIND(high)` or `FIXME`, to priority names.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) marker_priorities: BTreeMap<String, String>,
+    /// Maps the priorities of other tools, e.g. `A` in todo.txt or `H` in Taskwarrior, to priority names for
+    /// `rmnd import`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) import_priorities: BTreeMap<String, String>,
+    /// The shell command `rmnd daemon` runs when a reminder fires, with its fields in `RMND_*` environment variables.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) notifier: Option<String>,
 }
 
 impl ConfigFile {
     pub(crate) fn default_global_config() -> Self {
         Self {
             config_paths: vec![],
+            contexts: vec![],
             priorities: vec![Priority {
                 name: String::from("Critical"),
                 color: Color::Red,
                 id: String::from("0"),
+                severity: None,
             }],
             reminders: vec![Reminder {
                 priority: String::from("Critical"),
                 author: String::from("John Doe, johndoe, johndoe@gmail.com"),
                 text: String::from("This is a global critical reminder!"),
+                ..Default::default()
             }],
+            checklists: vec![],
             settings: Settings {
                 name: None,
                 username: None,
                 email: None,
+                markers: None,
+                marker_priorities: BTreeMap::new(),
+                import_priorities: BTreeMap::new(),
+                notifier: None,
             },
             path: None,
+            pathless: false,
+            lines: vec![],
         }
     }
 
     pub(crate) fn default_local_config() -> ConfigFile {
         ConfigFile {
             config_paths: vec![],
+            contexts: vec![],
             priorities: vec![],
             reminders: vec![Reminder {
                 priority: "Critical".to_string(),
                 author: String::from("John Doe, johndoe, johndoe@gmail.com"),
                 text: String::from("This is a local critical reminder!"),
+                ..Default::default()
             }],
+            checklists: vec![],
             settings: Settings {
                 name: None,
                 username: None,
                 email: None,
+                markers: None,
+                marker_priorities: BTreeMap::new(),
+                import_priorities: BTreeMap::new(),
+                notifier: None,
             },
             path: None,
+            pathless: false,
+            lines: vec![],
+        }
+    }
+
+    /// Gives the reminders of this file, including those of its pathless contexts, an id if they have none.
+    pub(crate) fn assign_ids(&mut self) {
+        reminder::assign_ids(&mut self.reminders);
+        for context in &mut self.contexts {
+            reminder::assign_ids(&mut context.reminders);
+        }
+    }
+
+    /// Records the line each reminder is defined on, `bytes` being the file this config was parsed from.
+    pub(crate) fn read_lines(&mut self, bytes: &[u8]) {
+        let Ok(spans) = toml::from_slice::<Spans>(bytes) else {
+            return;
+        };
+        let line = |span: &TextSpan| bytes[..span.text.start()].iter().filter(|v| **v == b'\n').count() + 1;
+
+        self.lines = spans.reminders.iter().map(line).collect();
+        for (context, spans) in self.contexts.iter_mut().zip(spans.contexts) {
+            context.lines = spans.reminders.iter().map(line).collect();
         }
     }
 }
 
 impl ConfigSum {
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(global_path: PathBuf) -> Self {
         Self {
+            global_path,
+            contexts: vec![],
+            pathless: vec![],
             priorities: vec![],
             reminders: vec![],
         }
     }
+
+    /// Finds the priority named `name` as seen from the config file at `path`. Priorities are merged along the context
+    /// chain, the global config first and then each enclosing local config, with the closest definition winning.
+    pub(crate) fn resolve_priority(&self, name: &str, path: &Path) -> Option<&Priority> {
+        let global = path == self.global_path;
+        let context = path.parent().unwrap_or(path);
+
+        self.priorities
+            .iter()
+            .filter(|v| v.priority.name == name)
+            .filter_map(|v| {
+                if v.path == self.global_path {
+                    return Some((0, &v.priority));
+                }
+
+                let container = v.path.parent()?;
+                if !global && context.starts_with(container) {
+                    Some((container.components().count() + 1, &v.priority))
+                } else {
+                    None
+                }
+            })
+            .max_by_key(|(depth, _)| *depth)
+            .map(|(_, priority)| priority)
+    }
+
+    /// How a context is displayed, pathless contexts are shown by their directory.
+    pub(crate) fn display_path(&self, path: &Path) -> String {
+        if self.pathless.iter().any(|v| v == path) {
+            format!("{} (no file)", path.parent().unwrap_or(path).to_str().unwrap())
+        } else {
+            path.to_str().unwrap().to_string()
+        }
+    }
+
+    /// Reminders whose priority is not defined anywhere along their context chain.
+    pub(crate) fn orphans(&self) -> impl Iterator<Item = &LocalReminder> {
+        self.reminders
+            .iter()
+            .filter(|v| self.resolve_priority(&v.reminder.priority, &v.path).is_none())
+    }
 }
@@ -0,0 +1,544 @@
+use std::{collections::HashMap,
+          fs,
+          io::{BufRead, BufReader, Read, Write},
+          os::unix::net::{UnixListener, UnixStream},
+          panic,
+          path::{Path, PathBuf},
+          process::Command,
+          sync::{Arc, Mutex},
+          thread,
+          time::{Duration, SystemTime}};
+
+use chrono::{DateTime, Local, NaiveDate, NaiveTime, TimeZone};
+
+use crate::{conditions,
+            reminder::{LocalReminder, Reminder},
+            Check,
+            Scope};
+
+/// How often the config files are checked for changes and the reminders for being due.
+const TICK: Duration = Duration::from_secs(1,);
+/// Where the daemon answers `rmnd daemon status`, relative to the config directory.
+const SOCKET: &str = "rmnd/daemon.sock";
+/// How many upcoming notifications `rmnd daemon status` lists.
+const UPCOMING: usize = 10;
+
+/// When a reminder fires. A reminder with `every` repeats from `remind_at`, or from its due day if it has none. Nothing
+/// fires while the reminder is snoozed, repeats resume once the snooze ends.
+struct Times {
+    once:          Vec<(DateTime<Local,>, &'static str,),>,
+    repeat:        Option<(DateTime<Local,>, chrono::Duration,),>,
+    snoozed_until: Option<DateTime<Local,>,>,
+}
+
+impl Times {
+    fn of(reminder: &Reminder, due_time: NaiveTime,) -> Self {
+        let mut times = Times {
+            once:          vec![],
+            repeat:        None,
+            snoozed_until: reminder.snoozed_until,
+        };
+        if reminder.done_at.is_some() {
+            return times;
+        }
+
+        let due = reminder.due.and_then(|v| at(v, due_time,),);
+        let every = reminder
+            .every
+            .as_deref()
+            .and_then(|v| conditions::parse_duration(v,).ok(),)
+            .and_then(|v| chrono::Duration::from_std(v,).ok(),)
+            .filter(|v| *v > chrono::Duration::zero(),);
+
+        match (reminder.remind_at, every,) {
+            | (Some(start,), Some(every,),) => times.repeat = Some((start, every,),),
+            | (Some(remind_at,), None,) => times.once.push((remind_at, "remind at",),),
+            | (None, Some(every,),) => times.repeat = due.map(|v| (v, every,),),
+            | (None, None,) => {},
+        }
+        if let Some(due,) = due {
+            if times.repeat.map(|(start, _,)| start != due,).unwrap_or(true,) {
+                times.once.push((due, "due",),);
+            }
+        }
+        times
+    }
+
+    /// `after`, or the moment before the snooze ends if that is later.
+    fn unsnoozed(&self, after: DateTime<Local,>,) -> DateTime<Local,> {
+        match self.snoozed_until {
+            | Some(until,) => after.max(until - chrono::Duration::nanoseconds(1,),),
+            | None => after,
+        }
+    }
+
+    /// The latest time in `(after, until]` the reminder fires at, if any.
+    fn fired(&self, after: DateTime<Local,>, until: DateTime<Local,>,) -> Option<(DateTime<Local,>, &'static str,),> {
+        let after = self.unsnoozed(after,);
+        let once = self
+            .once
+            .iter()
+            .filter(|(time, _,)| *time > after && *time <= until,)
+            .copied();
+        let repeat = self.repeat.and_then(|(start, every,)| {
+            if until < start {
+                return None;
+            }
+            let time = start + every * ((until - start).num_seconds() / every.num_seconds()) as i32;
+            (time > after).then_some((time, "repeat",),)
+        },);
+        once.chain(repeat,).max_by_key(|(time, _,)| *time,)
+    }
+
+    /// The first time after `after` the reminder fires at, if any.
+    fn next(&self, after: DateTime<Local,>,) -> Option<(DateTime<Local,>, &'static str,),> {
+        let after = self.unsnoozed(after,);
+        let once = self.once.iter().filter(|(time, _,)| *time > after,).copied();
+        let repeat = self.repeat.map(|(start, every,)| {
+            if after < start {
+                return (start, "repeat",);
+            }
+            (
+                start + every * ((after - start).num_seconds() / every.num_seconds() + 1) as i32,
+                "repeat",
+            )
+        },);
+        once.chain(repeat,).min_by_key(|(time, _,)| *time,)
+    }
+}
+
+fn at(day: NaiveDate, time: NaiveTime,) -> Option<DateTime<Local,>,> {
+    Local.from_local_datetime(&day.and_time(time,),).earliest()
+}
+
+/// Everything the daemon knows after loading the configs.
+struct Schedule {
+    reminders:   Vec<(LocalReminder, Times,),>,
+    global_path: PathBuf,
+    contexts:    usize,
+    notifier:    Option<String,>,
+    /// The modification times of the files the schedule was loaded from, `None` for missing ones.
+    watched:     HashMap<PathBuf, Option<SystemTime,>,>,
+}
+
+impl Schedule {
+    /// Loads every context, or returns `None` if a config does not parse, e.g. while it is being edited.
+    fn load(due_time: NaiveTime,) -> Option<Self,> {
+        let loaded = panic::catch_unwind(|| (crate::get_all(), crate::load_global_config().settings.notifier,),);
+        let (sum, notifier,) = loaded.ok()?;
+
+        let mut files: Vec<PathBuf,> = vec![sum.global_path.clone()];
+        files.extend(sum.contexts.iter().filter(|v| !sum.pathless.contains(v,),).cloned(),);
+        let watched = files
+            .into_iter()
+            .map(|v| {
+                let modified = modified(&v,);
+                (v, modified,)
+            },)
+            .collect();
+        let reminders = sum
+            .reminders
+            .into_iter()
+            .map(|v| {
+                let times = Times::of(&v.reminder, due_time,);
+                (v, times,)
+            },)
+            .collect();
+        Some(Schedule {
+            reminders,
+            global_path: sum.global_path,
+            contexts: sum.contexts.len(),
+            notifier,
+            watched,
+        },)
+    }
+
+    fn changed(&self,) -> bool {
+        self.watched.iter().any(|(path, time,)| modified(path,) != *time,)
+    }
+
+    fn upcoming(&self, after: DateTime<Local,>,) -> Vec<Upcoming,> {
+        let mut upcoming: Vec<Upcoming,> = self
+            .reminders
+            .iter()
+            .filter_map(|(reminder, times,)| {
+                times.next(after,).map(|(time, reason,)| Upcoming {
+                    time,
+                    reason,
+                    line: describe(reminder,),
+                },)
+            },)
+            .collect();
+        upcoming.sort_by_key(|v| v.time,);
+        upcoming.truncate(UPCOMING,);
+        upcoming
+    }
+}
+
+fn modified(path: &Path,) -> Option<SystemTime,> {
+    fs::metadata(path,).and_then(|v| v.modified(),).ok()
+}
+
+fn describe(reminder: &LocalReminder,) -> String {
+    format!(
+        "{}: {} ({})",
+        reminder.reminder.priority,
+        reminder.reminder.text,
+        context_dir(reminder).display()
+    )
+}
+
+fn context_dir(reminder: &LocalReminder,) -> &Path {
+    reminder.path.parent().unwrap_or(&reminder.path,)
+}
+
+struct Upcoming {
+    time:   DateTime<Local,>,
+    reason: &'static str,
+    line:   String,
+}
+
+/// What `rmnd daemon status` reports, shared with the thread serving the socket.
+struct Status {
+    started_at:  DateTime<Local,>,
+    reloaded_at: DateTime<Local,>,
+    contexts:    usize,
+    reminders:   usize,
+    fired:       usize,
+    upcoming:    Vec<Upcoming,>,
+}
+
+impl Status {
+    fn render(&self,) -> String {
+        let time = |v: &DateTime<Local,>| v.format("%Y-%m-%d %H:%M:%S",).to_string();
+        let mut text = format!(
+            "Running since {}, pid {}.\nWatching {} contexts, {} reminders are scheduled, last reloaded {}.\nFired {} \
+             notifications.\n",
+            time(&self.started_at),
+            std::process::id(),
+            self.contexts,
+            self.reminders,
+            time(&self.reloaded_at),
+            self.fired,
+        );
+        if self.upcoming.is_empty() {
+            text.push_str("Nothing is scheduled.\n",);
+        } else {
+            text.push_str("Upcoming:\n",);
+            for upcoming in &self.upcoming {
+                text.push_str(&format!(
+                    "  {} {} [{}]\n",
+                    upcoming.time.format("%Y-%m-%d %H:%M"),
+                    upcoming.line,
+                    upcoming.reason
+                ),);
+            }
+        }
+        text
+    }
+}
+
+pub(crate) fn socket_path() -> PathBuf {
+    PathBuf::from(crate::CONFIG_DIR,).join(SOCKET,)
+}
+
+/// Runs in the foreground until killed, firing reminders as their `due`, `remind_at` or `every` time comes. Firing
+/// runs `notifier`, or the `notifier` setting of the global config, with the reminder's fields in environment
+/// variables, and always logs to stdout. The configs are reloaded when any of them changes.
+pub(crate) fn run(notifier: Option<String,>, due_time: NaiveTime,) {
+    let socket = socket_path();
+    if UnixStream::connect(&socket,).is_ok() {
+        crate::fail(&format!(
+            "The daemon is already running, see `rmnd daemon status` or remove {} if it is not.",
+            socket.display()
+        ),);
+    }
+    // Left behind by a daemon that was killed.
+    let _ = fs::remove_file(&socket,);
+    fs::create_dir_all(socket.parent().unwrap(),).unwrap();
+    let listener =
+        UnixListener::bind(&socket,).unwrap_or_else(|e| panic!("Failed to listen on {}: {}", socket.display(), e),);
+
+    let Some(mut schedule,) = Schedule::load(due_time,) else {
+        crate::fail("The configs could not be loaded, fix them and start the daemon again.",);
+    };
+    let started_at = Local::now();
+    let status = Arc::new(Mutex::new(Status {
+        started_at,
+        reloaded_at: started_at,
+        contexts: schedule.contexts,
+        reminders: 0,
+        fired: 0,
+        upcoming: vec![],
+    },),);
+
+    let served = Arc::clone(&status,);
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            serve(stream, &served,);
+        }
+    },);
+
+    log(&format!(
+        "Started, watching {} contexts. Status is served on {}.",
+        schedule.contexts,
+        socket.display()
+    ),);
+    let mut last = started_at;
+    loop {
+        {
+            let mut status = status.lock().unwrap();
+            status.contexts = schedule.contexts;
+            status.reminders = schedule
+                .reminders
+                .iter()
+                .filter(|(_, times,)| times.next(last,).is_some(),)
+                .count();
+            status.upcoming = schedule.upcoming(last,);
+        }
+        thread::sleep(TICK,);
+
+        if schedule.changed() {
+            match Schedule::load(due_time,) {
+                | Some(reloaded,) => {
+                    schedule = reloaded;
+                    status.lock().unwrap().reloaded_at = Local::now();
+                    log(&format!("Reloaded {} contexts.", schedule.contexts),);
+                },
+                | None => {
+                    // Only retry once the files change again.
+                    schedule.watched = schedule.watched.keys().map(|v| (v.clone(), modified(v,),),).collect();
+                    log("A config could not be loaded, keeping the previous schedule.",);
+                },
+            }
+        }
+
+        let now = Local::now();
+        for (reminder, times,) in &schedule.reminders {
+            if let Some((_, reason,),) = times.fired(last, now,) {
+                if !holds(reminder, &schedule.global_path,) {
+                    log(&format!(
+                        "{} [{}, skipped as its conditions do not hold]",
+                        describe(reminder),
+                        reason
+                    ),);
+                    continue;
+                }
+                fire(reminder, reason, notifier.as_ref().or(schedule.notifier.as_ref(),),);
+                status.lock().unwrap().fired += 1;
+            }
+        }
+        last = now;
+    }
+}
+
+/// Whether the reminder's branch and `when` conditions hold, as `show` evaluates them in the context's directory.
+/// Anchors are left out, they depend on where the user is working.
+fn holds(reminder: &LocalReminder, global_path: &Path,) -> bool {
+    let ignored = |check: &Check| check.condition.starts_with("anchor ",);
+    let target = context_dir(reminder,).to_path_buf();
+    Scope::new(global_path, target,)
+        .checks(reminder, false,)
+        .iter()
+        .filter(|c| !ignored(c,),)
+        .all(|c| c.holds,)
+}
+
+fn serve(mut stream: UnixStream, status: &Mutex<Status,>,) {
+    let mut request = String::new();
+    if BufReader::new(&stream,).read_line(&mut request,).is_err() {
+        return;
+    }
+    let response = match request.trim() {
+        | "status" => status.lock().unwrap().render(),
+        | other => format!("Unknown request {:?}.\n", other),
+    };
+    let _ = stream.write_all(response.as_bytes(),);
+}
+
+fn fire(reminder: &LocalReminder, reason: &str, notifier: Option<&String,>,) {
+    log(&format!("{} [{}]", describe(reminder), reason),);
+    let Some(notifier,) = notifier else {
+        return;
+    };
+
+    let fields = &reminder.reminder;
+    let time = |v: Option<DateTime<Local,>,>| v.map(|v| v.to_rfc3339(),).unwrap_or_default();
+    let child = Command::new("sh",)
+        .arg("-c",)
+        .arg(notifier,)
+        .current_dir(context_dir(reminder,),)
+        .env("RMND_ID", &fields.id,)
+        .env("RMND_TEXT", &fields.text,)
+        .env("RMND_PRIORITY", &fields.priority,)
+        .env("RMND_AUTHOR", &fields.author,)
+        .env("RMND_CONTEXT", context_dir(reminder,),)
+        .env("RMND_DUE", fields.due.map(|v| v.to_string(),).unwrap_or_default(),)
+        .env("RMND_REMIND_AT", time(fields.remind_at,),)
+        .env("RMND_EVERY", fields.every.clone().unwrap_or_default(),)
+        .env("RMND_TAGS", fields.tags.join(",",),)
+        .env("RMND_REASON", reason,)
+        .spawn();
+    match child {
+        // Waited for on another thread so a slow notifier does not delay other reminders.
+        | Ok(mut child,) => {
+            thread::spawn(move || child.wait(),);
+        },
+        | Err(e,) => log(&format!("Failed to run the notifier: {}", e),),
+    }
+}
+
+fn log(message: &str,) {
+    println!("[{}] {}", Local::now().format("%Y-%m-%d %H:%M:%S"), message);
+}
+
+/// Asks a running daemon for its status, exits with 1 if none is running.
+pub(crate) fn status() {
+    let socket = socket_path();
+    let Ok(mut stream,) = UnixStream::connect(&socket,) else {
+        println!("The daemon is not running.");
+        std::process::exit(1,);
+    };
+    stream.write_all(b"status\n",).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response,).unwrap();
+    print!("{}", response);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(day: u32, hour: u32, minute: u32,) -> DateTime<Local,> {
+        Local.with_ymd_and_hms(2030, 1, day, hour, minute, 0,).unwrap()
+    }
+
+    fn nine() -> NaiveTime {
+        NaiveTime::from_hms_opt(9, 0, 0,).unwrap()
+    }
+
+    #[test]
+    fn remind_at_fires_once() {
+        let reminder = Reminder {
+            remind_at: Some(time(1, 10, 0,),),
+            ..Default::default()
+        };
+        let times = Times::of(&reminder, nine(),);
+        assert_eq!(times.next(time(1, 0, 0)), Some((time(1, 10, 0), "remind at")));
+        assert_eq!(times.next(time(1, 10, 0)), None);
+        assert_eq!(
+            times.fired(time(1, 9, 59), time(1, 10, 0)),
+            Some((time(1, 10, 0), "remind at"))
+        );
+        assert_eq!(times.fired(time(1, 10, 0), time(1, 10, 1)), None);
+    }
+
+    #[test]
+    fn due_fires_at_the_due_time() {
+        let reminder = Reminder {
+            due: NaiveDate::from_ymd_opt(2030, 1, 2,),
+            ..Default::default()
+        };
+        let times = Times::of(&reminder, nine(),);
+        assert_eq!(times.next(time(1, 12, 0)), Some((time(2, 9, 0), "due")));
+        assert_eq!(times.fired(time(2, 8, 0), time(2, 9, 30)), Some((time(2, 9, 0), "due")));
+    }
+
+    #[test]
+    fn repeats_from_remind_at() {
+        let reminder = Reminder {
+            remind_at: Some(time(1, 10, 0,),),
+            every: Some("2h".to_string(),),
+            ..Default::default()
+        };
+        let times = Times::of(&reminder, nine(),);
+        assert_eq!(times.next(time(1, 0, 0)), Some((time(1, 10, 0), "repeat")));
+        assert_eq!(times.next(time(1, 10, 0)), Some((time(1, 12, 0), "repeat")));
+        assert_eq!(times.next(time(1, 13, 0)), Some((time(1, 14, 0), "repeat")));
+        assert_eq!(times.fired(time(1, 9, 0), time(1, 9, 59)), None);
+        assert_eq!(
+            times.fired(time(1, 11, 59), time(1, 12, 0)),
+            Some((time(1, 12, 0), "repeat"))
+        );
+        // A gap longer than the interval fires once, for the latest occurrence.
+        assert_eq!(
+            times.fired(time(1, 11, 0), time(1, 17, 0)),
+            Some((time(1, 16, 0), "repeat"))
+        );
+    }
+
+    #[test]
+    fn repeats_from_due_day() {
+        let reminder = Reminder {
+            due: NaiveDate::from_ymd_opt(2030, 1, 2,),
+            every: Some("1d".to_string(),),
+            ..Default::default()
+        };
+        let times = Times::of(&reminder, nine(),);
+        assert!(times.once.is_empty());
+        assert_eq!(times.next(time(2, 9, 0)), Some((time(3, 9, 0), "repeat")));
+    }
+
+    #[test]
+    fn due_and_repeat_from_remind_at() {
+        let reminder = Reminder {
+            due: NaiveDate::from_ymd_opt(2030, 1, 5,),
+            remind_at: Some(time(1, 10, 0,),),
+            every: Some("1d".to_string(),),
+            ..Default::default()
+        };
+        let times = Times::of(&reminder, nine(),);
+        assert_eq!(times.fired(time(5, 8, 0), time(5, 9, 0)), Some((time(5, 9, 0), "due")));
+        assert_eq!(
+            times.fired(time(5, 9, 0), time(5, 10, 0)),
+            Some((time(5, 10, 0), "repeat"))
+        );
+    }
+
+    #[test]
+    fn done_and_invalid_intervals_never_fire() {
+        let done = Reminder {
+            remind_at: Some(time(1, 10, 0,),),
+            done_at: Some(time(1, 0, 0,),),
+            ..Default::default()
+        };
+        assert_eq!(Times::of(&done, nine()).next(time(1, 0, 0)), None);
+
+        let zero = Reminder {
+            remind_at: Some(time(1, 10, 0,),),
+            every: Some("0s".to_string(),),
+            ..Default::default()
+        };
+        assert_eq!(
+            Times::of(&zero, nine()).next(time(1, 0, 0)),
+            Some((time(1, 10, 0), "remind at"))
+        );
+    }
+
+    #[test]
+    fn snoozed_reminders_wait() {
+        let reminder = Reminder {
+            remind_at: Some(time(1, 10, 0,),),
+            every: Some("1h".to_string(),),
+            snoozed_until: Some(time(1, 12, 30,),),
+            ..Default::default()
+        };
+        let times = Times::of(&reminder, nine(),);
+        assert_eq!(times.fired(time(1, 9, 0), time(1, 12, 29)), None);
+        assert_eq!(times.next(time(1, 9, 0)), Some((time(1, 13, 0), "repeat")));
+        assert_eq!(
+            times.fired(time(1, 12, 59), time(1, 13, 0)),
+            Some((time(1, 13, 0), "repeat"))
+        );
+
+        let once = Reminder {
+            remind_at: Some(time(1, 10, 0,),),
+            snoozed_until: Some(time(1, 10, 0,),),
+            ..Default::default()
+        };
+        assert_eq!(
+            Times::of(&once, nine()).next(time(1, 0, 0)),
+            Some((time(1, 10, 0), "remind at"))
+        );
+    }
+}
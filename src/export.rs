@@ -0,0 +1,336 @@
+use chrono::{DateTime, Local, NaiveDate, Utc};
+
+use crate::reminder::{Color, Priority, Reminder};
+
+/// The formats `rmnd export --to` writes.
+pub(crate) const FORMATS: [&str; 5] = ["todotxt", "markdown", "org", "html", "ics"];
+
+/// A reminder to export along with what `rmnd show` would resolve for it.
+pub(crate) struct Entry<'a> {
+    /// The context as `show` displays it, entries of one context are expected to be next to each other.
+    pub(crate) context: String,
+    pub(crate) reminder: &'a Reminder,
+    pub(crate) priority: Option<&'a Priority>,
+    /// The position of the priority from most to least important, out of `levels` in `render`.
+    pub(crate) rank: Option<usize>,
+}
+
+/// Writes `entries` in one of `FORMATS`. `levels` is how many priorities there are, to scale ranks onto the fixed
+/// priorities of other formats.
+pub(crate) fn render(format: &str, entries: &[Entry], levels: usize) -> String {
+    match format {
+        "todotxt" => entries.iter().map(todo_txt).collect(),
+        "markdown" => markdown(entries),
+        "org" => org(entries),
+        "html" => html(entries),
+        "ics" => ics(entries, levels),
+        _ => unreachable!("Unsupported export format `{}`", format),
+    }
+}
+
+/// Whether a reminder becomes an entry of an `.ics` calendar, only those with a date or time do.
+pub(crate) fn dated(reminder: &Reminder) -> bool {
+    reminder.due.is_some() || reminder.snoozed_until.is_some() || reminder.remind_at.is_some()
+}
+
+fn letter(rank: Option<usize>) -> Option<char> {
+    rank.filter(|v| *v < 26).map(|v| (b'A' + v as u8) as char)
+}
+
+/// Groups consecutive entries of the same context.
+fn contexts<'a, 'b>(entries: &'b [Entry<'a>]) -> impl Iterator<Item = &'b [Entry<'a>]> {
+    entries.chunk_by(|a, b| a.context == b.context)
+}
+
+/// A todo.txt line, `x 2024-01-02 Pay rent +home due:2024-01-05 pri:A`. Done tasks keep their priority as `pri:`, like
+/// todo.txt clients do when completing one.
+fn todo_txt(entry: &Entry) -> String {
+    let reminder = entry.reminder;
+    let mut words = vec![];
+    if let Some(done_at) = reminder.done_at {
+        words.push(format!("x {}", done_at.format("%Y-%m-%d")));
+    } else if let Some(letter) = letter(entry.rank) {
+        words.push(format!("({})", letter));
+    }
+    words.push(reminder.text.clone());
+    words.extend(reminder.tags.iter().map(|v| if v.starts_with(['+', '@']) { v.clone() } else { format!("+{}", v) }));
+    if let Some(due) = reminder.due {
+        words.push(format!("due:{}", due));
+    }
+    if let (Some(_), Some(letter)) = (reminder.done_at, letter(entry.rank)) {
+        words.push(format!("pri:{}", letter));
+    }
+    words.join(" ") + "\n"
+}
+
+/// A heading per context and priority, most important first, with a checklist item per reminder,
+/// `- [ ] Write docs 📅 2024-02-01`.
+fn markdown(entries: &[Entry]) -> String {
+    let mut document = String::new();
+    for group in contexts(entries) {
+        document.push_str(&format!("## {}\n", group[0].context));
+        let mut priorities: Vec<(Option<usize>, &String)> = vec![];
+        for entry in group {
+            if !priorities.iter().any(|v| *v.1 == entry.reminder.priority) {
+                priorities.push((entry.rank, &entry.reminder.priority));
+            }
+        }
+        priorities.sort_by_key(|v| v.0);
+        for (_, priority) in priorities {
+            document.push_str(&format!("\n### {}\n\n", priority));
+            for entry in group.iter().filter(|v| v.reminder.priority == *priority) {
+                let reminder = entry.reminder;
+                let check = if reminder.done_at.is_some() { "x" } else { " " };
+                let mut line = format!("- [{}] {}", check, reminder.text);
+                for tag in &reminder.tags {
+                    line.push_str(&format!(" {}", tag));
+                }
+                if let Some(due) = reminder.due {
+                    line.push_str(&format!(" 📅 {}", due));
+                }
+                document.push_str(&line);
+                document.push('\n');
+            }
+        }
+        document.push('\n');
+    }
+    document
+}
+
+/// An org tag, which may only contain letters, numbers, `_`, `@`, `#` and `%`. The `+` of todo.txt projects is dropped.
+fn org_tag(tag: &str) -> String {
+    tag.trim_start_matches('+').chars().map(|c| if c.is_alphanumeric() || "_@#%".contains(c) { c } else { '_' }).collect()
+}
+
+fn org_date(date: NaiveDate) -> String {
+    date.format("%Y-%m-%d %a").to_string()
+}
+
+/// A headline per context with a `TODO` or `DONE` headline per reminder below it.
+fn org(entries: &[Entry]) -> String {
+    let mut document = String::new();
+    for group in contexts(entries) {
+        document.push_str(&format!("* {}\n", group[0].context));
+        for entry in group {
+            let reminder = entry.reminder;
+            let keyword = if reminder.done_at.is_some() { "DONE" } else { "TODO" };
+            let mut headline = format!("** {} ", keyword);
+            if let Some(letter) = letter(entry.rank) {
+                headline.push_str(&format!("[#{}] ", letter));
+            }
+            headline.push_str(&reminder.text);
+            let tags: Vec<String> = reminder.tags.iter().map(|v| org_tag(v)).filter(|v| !v.is_empty()).collect();
+            if !tags.is_empty() {
+                headline.push_str(&format!(" :{}:", tags.join(":")));
+            }
+            document.push_str(&headline);
+            document.push('\n');
+
+            let mut planning = vec![];
+            if let Some(done_at) = reminder.done_at {
+                planning.push(format!("CLOSED: [{}]", done_at.format("%Y-%m-%d %a %H:%M")));
+            }
+            if let Some(due) = reminder.due {
+                planning.push(format!("DEADLINE: <{}>", org_date(due)));
+            }
+            if let Some(until) = reminder.snoozed_until {
+                planning.push(format!("SCHEDULED: <{}>", until.format("%Y-%m-%d %a %H:%M")));
+            }
+            if !planning.is_empty() {
+                document.push_str(&format!("   {}\n", planning.join(" ")));
+            }
+        }
+    }
+    document
+}
+
+/// The CSS color closest to how a terminal shows `color`.
+fn css_color(color: Color) -> String {
+    let hex = match color {
+        Color::Black => "#000000",
+        Color::Red => "#cd3131",
+        Color::Green => "#0dbc79",
+        Color::Yellow => "#b58900",
+        Color::Blue => "#2472c8",
+        Color::Magenta => "#bc3fbc",
+        Color::Cyan => "#11a8cd",
+        Color::White => "#767676",
+        Color::BrightBlack => "#666666",
+        Color::BrightRed => "#f14c4c",
+        Color::BrightGreen => "#23d18b",
+        Color::BrightYellow => "#d7ba00",
+        Color::BrightBlue => "#3b8eea",
+        Color::BrightMagenta => "#d670d6",
+        Color::BrightCyan => "#29b8db",
+        Color::BrightWhite => "#999999",
+        Color::TrueColor { r, g, b } => return format!("#{:02x}{:02x}{:02x}", r, g, b),
+    };
+    hex.to_string()
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// A page without external resources, a section per context with each reminder in the color of its priority.
+fn html(entries: &[Entry]) -> String {
+    let mut body = String::new();
+    for group in contexts(entries) {
+        body.push_str(&format!("<section>\n<h2>{}</h2>\n<ul>\n", escape_html(&group[0].context)));
+        for entry in group {
+            let reminder = entry.reminder;
+            let color = entry.priority.map(|v| css_color(v.color)).unwrap_or_else(|| "inherit".to_string());
+            let class = if reminder.done_at.is_some() { " class=\"done\"" } else { "" };
+            let mut details = vec![];
+            if let Some(due) = reminder.due {
+                details.push(format!("due {}", due));
+            }
+            details.extend(reminder.tags.iter().map(|v| escape_html(v)));
+            let details = if details.is_empty() { String::new() } else { format!(" <small>{}</small>", details.join(", ")) };
+            body.push_str(&format!(
+                "<li{} style=\"border-color: {}\"><span class=\"priority\" style=\"color: {}\">{}</span> {}{}</li>\n",
+                class,
+                color,
+                color,
+                escape_html(&reminder.priority),
+                escape_html(&reminder.text),
+                details
+            ));
+        }
+        body.push_str("</ul>\n</section>\n");
+    }
+
+    format!(
+        "<!DOCTYPE html>\n\
+         <html lang=\"en\">\n\
+         <head>\n\
+         <meta charset=\"utf-8\">\n\
+         <title>Reminders</title>\n\
+         <style>\n\
+         body {{ font-family: system-ui, sans-serif; max-width: 50rem; margin: 2rem auto; padding: 0 1rem; }}\n\
+         h2 {{ font-size: 1rem; font-family: monospace; }}\n\
+         ul {{ list-style: none; padding: 0; }}\n\
+         li {{ border-left: 0.25rem solid; padding: 0.25rem 0.5rem; margin: 0.25rem 0; }}\n\
+         .priority {{ font-weight: bold; }}\n\
+         .done {{ text-decoration: line-through; opacity: 0.6; }}\n\
+         small {{ color: #888888; }}\n\
+         </style>\n\
+         </head>\n\
+         <body>\n\
+         <h1>Reminders</h1>\n\
+         <p><small>Exported {}</small></p>\n\
+         {}\
+         </body>\n\
+         </html>\n",
+        Local::now().format("%Y-%m-%d %H:%M"),
+        body
+    )
+}
+
+fn escape_ics(text: &str) -> String {
+    text.replace('\\', "\\\\").replace(';', "\\;").replace(',', "\\,").replace('\n', "\\n")
+}
+
+fn ics_time(time: DateTime<Local>) -> String {
+    time.with_timezone(&Utc).format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// A stable identifier for a reminder in a calendar, its id is only unique within its context.
+fn uid(entry: &Entry) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in entry.context.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{}-{:07x}@rmnd", entry.reminder.id, hash >> 36)
+}
+
+/// The recurrence rule for a reminder's `every`, in the largest unit that divides it.
+fn rrule(every: &str) -> Option<String> {
+    let seconds = crate::conditions::parse_duration(every).ok()?.as_secs();
+    let units = [("WEEKLY", 7 * 24 * 60 * 60), ("DAILY", 24 * 60 * 60), ("HOURLY", 60 * 60), ("MINUTELY", 60), ("SECONDLY", 1)];
+    let (frequency, length) = units.into_iter().find(|(_, length)| seconds % length == 0).filter(|_| seconds > 0)?;
+    Some(format!("RRULE:FREQ={};INTERVAL={}", frequency, seconds / length))
+}
+
+/// Appends a content line, folded after 75 bytes as the format requires.
+fn push_line(calendar: &mut String, line: &str) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            calendar.push_str("\r\n ");
+            width = 1;
+        }
+        calendar.push(c);
+        width += c.len_utf8();
+    }
+    calendar.push_str("\r\n");
+}
+
+/// A VTODO for every reminder with a due date and a VEVENT with an alarm for its `remind_at` and for when a snoozed one
+/// comes back. `every` becomes a recurrence rule of the event, or of the todo without a `remind_at`. Priorities are
+/// scaled onto 1, the most important, to 9.
+fn ics(entries: &[Entry], levels: usize) -> String {
+    let stamp = ics_time(Local::now());
+    let mut lines = vec!["BEGIN:VCALENDAR".to_string(), "VERSION:2.0".to_string(), "PRODID:-//rmnd//rmnd//EN".to_string()];
+
+    for entry in entries {
+        let reminder = entry.reminder;
+        let mut common = vec![
+            format!("DTSTAMP:{}", stamp),
+            format!("SUMMARY:{}", escape_ics(&reminder.text)),
+            format!("DESCRIPTION:{}", escape_ics(&format!("{} reminder in {}", reminder.priority, entry.context))),
+        ];
+        let categories: Vec<String> = std::iter::once(&reminder.priority).chain(&reminder.tags).map(|v| escape_ics(v)).collect();
+        common.push(format!("CATEGORIES:{}", categories.join(",")));
+        if let Some(rank) = entry.rank {
+            common.push(format!("PRIORITY:{}", 1 + rank * 8 / levels.saturating_sub(1).max(1)));
+        }
+
+        if let Some(due) = reminder.due {
+            lines.push("BEGIN:VTODO".to_string());
+            lines.push(format!("UID:{}", uid(entry)));
+            lines.extend(common.iter().cloned());
+            lines.push(format!("DUE;VALUE=DATE:{}", due.format("%Y%m%d")));
+            if let Some(rule) = reminder.every.as_deref().filter(|_| reminder.remind_at.is_none()).and_then(rrule) {
+                lines.push(format!("DTSTART;VALUE=DATE:{}", due.format("%Y%m%d")));
+                lines.push(rule);
+            }
+            match reminder.done_at {
+                Some(done_at) => {
+                    lines.push("STATUS:COMPLETED".to_string());
+                    lines.push(format!("COMPLETED:{}", ics_time(done_at)));
+                },
+                None => lines.push("STATUS:NEEDS-ACTION".to_string()),
+            }
+            lines.push("END:VTODO".to_string());
+        }
+
+        if reminder.done_at.is_some() {
+            continue;
+        }
+        let remind_at = reminder.remind_at.map(|v| ("remind", v, reminder.every.as_deref().and_then(rrule)));
+        let snoozed = reminder.snoozed_until.map(|v| ("snoozed", v, None));
+        for (kind, start, rule) in remind_at.into_iter().chain(snoozed) {
+            lines.push("BEGIN:VEVENT".to_string());
+            lines.push(format!("UID:{}-{}", kind, uid(entry)));
+            lines.extend(common.iter().cloned());
+            lines.push(format!("DTSTART:{}", ics_time(start)));
+            lines.push(format!("DTEND:{}", ics_time(start)));
+            lines.extend(rule);
+            lines.push("BEGIN:VALARM".to_string());
+            lines.push("ACTION:DISPLAY".to_string());
+            lines.push(format!("DESCRIPTION:{}", escape_ics(&reminder.text)));
+            lines.push("TRIGGER:PT0S".to_string());
+            lines.push("END:VALARM".to_string());
+            lines.push("END:VEVENT".to_string());
+        }
+    }
+
+    lines.push("END:VCALENDAR".to_string());
+    let mut calendar = String::new();
+    for line in lines {
+        push_line(&mut calendar, &line);
+    }
+    calendar
+}
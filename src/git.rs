@@ -0,0 +1,240 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// The git repository a context lives in.
+pub(crate) struct Repo {
+    /// The root of the working tree.
+    pub(crate) work_tree:  PathBuf,
+    /// The `.git` directory of this working tree, for linked worktrees this is `.git/worktrees/<name>` in the main
+    /// repository.
+    pub(crate) git_dir:    PathBuf,
+    /// The directory shared between all worktrees, where `info/exclude` and `hooks` live.
+    pub(crate) common_dir: PathBuf,
+}
+
+/// The hooks `rmnd git install-hooks` writes, each one calls back into `rmnd git hook <event>`.
+pub(crate) const HOOKS: [&str; 3] = ["pre-commit", "pre-push", "post-checkout"];
+
+const HOOK_MARKER: &str = "# Installed by rmnd";
+
+/// The name `.gitattributes` assigns context files to with `merge=rmnd`, see `rmnd merge-driver`.
+const MERGE_DRIVER: &str = "rmnd";
+
+/// What is checked out in a working tree.
+pub(crate) enum Head {
+    Branch(String),
+    Detached,
+}
+
+/// How git treats a context file.
+#[derive(PartialEq)]
+pub(crate) enum Tracking {
+    Tracked,
+    Ignored,
+    Untracked,
+    NotInRepo,
+}
+
+impl Repo {
+    /// Finds the repository containing `dir` by walking up to the closest `.git`, following the `gitdir:` file used
+    /// by linked worktrees and submodules.
+    pub(crate) fn find(dir: &Path) -> Option<Self> {
+        for ancestor in dir.ancestors() {
+            let dot_git = ancestor.join(".git");
+            let git_dir = if dot_git.is_dir() {
+                dot_git
+            } else if dot_git.is_file() {
+                let contents = fs::read_to_string(&dot_git).ok()?;
+                let target = contents.trim().strip_prefix("gitdir:")?.trim();
+                ancestor.join(target)
+            } else {
+                continue;
+            };
+
+            let common_dir = match fs::read_to_string(git_dir.join("commondir")) {
+                Ok(common) => git_dir.join(common.trim()),
+                Err(_) => git_dir.clone(),
+            };
+
+            return Some(Self {
+                work_tree: ancestor.to_path_buf(),
+                git_dir: git_dir.canonicalize().unwrap_or(git_dir),
+                common_dir: common_dir.canonicalize().unwrap_or(common_dir),
+            });
+        }
+
+        None
+    }
+
+    /// Reads `HEAD` of this working tree, each linked worktree has its own.
+    pub(crate) fn head(&self) -> Option<Head> {
+        let contents = fs::read_to_string(self.git_dir.join("HEAD")).ok()?;
+        let contents = contents.trim();
+        match contents.strip_prefix("ref:") {
+            | Some(reference) => {
+                let reference = reference.trim();
+                Some(Head::Branch(reference.strip_prefix("refs/heads/").unwrap_or(reference).to_string()))
+            },
+            | None => Some(Head::Detached),
+        }
+    }
+
+    /// The path of `file` relative to the working tree, with forward slashes as git expects.
+    pub(crate) fn relative(&self, file: &Path) -> Option<String> {
+        let relative = file.strip_prefix(&self.work_tree).ok()?;
+        Some(relative.components().map(|v| v.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/"))
+    }
+
+    /// The directory git runs hooks from, honoring `core.hooksPath`.
+    pub(crate) fn hooks_dir(&self) -> PathBuf {
+        match self.git(&["rev-parse", "--git-path", "hooks"]) {
+            | Some(output) if output.status.success() => {
+                let path = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim());
+                self.work_tree.join(path)
+            },
+            | _ => self.common_dir.join("hooks"),
+        }
+    }
+
+    /// Writes a hook that runs `rmnd git hook <event>`. A hook that was not written by rmnd is only replaced with
+    /// `force`, returns whether the hook was written.
+    pub(crate) fn install_hook(&self, event: &str, force: bool) -> std::io::Result<bool> {
+        let dir = self.hooks_dir();
+        let path = dir.join(event);
+
+        if let Ok(existing) = fs::read_to_string(&path) {
+            if !existing.contains(HOOK_MARKER) && !force {
+                return Ok(false);
+            }
+        }
+
+        let bypass = match event {
+            | "pre-commit" => "\n# Bypass with `git commit --no-verify`.",
+            | "pre-push" => "\n# Bypass with `git push --no-verify`.",
+            | _ => "",
+        };
+        let script = format!("#!/bin/sh\n{}, reinstall with `rmnd git install-hooks`.{}\nexec rmnd git hook {} \"$@\"\n", HOOK_MARKER, bypass, event);
+
+        fs::create_dir_all(&dir)?;
+        fs::write(&path, script)?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+        }
+
+        Ok(true)
+    }
+
+    /// Assigns the rmnd merge driver to files matching `patterns` in the `.gitattributes` at the root of the working
+    /// tree, returns the lines that were added.
+    pub(crate) fn add_merge_attributes(&self, patterns: &[&str]) -> std::io::Result<Vec<String>> {
+        let path = self.work_tree.join(".gitattributes");
+        let mut contents = fs::read_to_string(&path).unwrap_or_default();
+        let added: Vec<String> = patterns
+            .iter()
+            .map(|v| format!("{} merge={}", v, MERGE_DRIVER))
+            .filter(|line| !contents.lines().any(|v| v.split_whitespace().eq(line.split_whitespace())))
+            .collect();
+        if added.is_empty() {
+            return Ok(added);
+        }
+
+        if !contents.is_empty() && !contents.ends_with('\n') {
+            contents.push('\n');
+        }
+        for line in &added {
+            contents.push_str(line);
+            contents.push('\n');
+        }
+        fs::write(&path, contents)?;
+        Ok(added)
+    }
+
+    /// Registers `rmnd merge-driver` in the repository's config, which unlike `.gitattributes` is not shared and has
+    /// to be set up in every clone.
+    pub(crate) fn configure_merge_driver(&self) -> bool {
+        let name = format!("merge.{}.name", MERGE_DRIVER);
+        let driver = format!("merge.{}.driver", MERGE_DRIVER);
+        [(name.as_str(), "rmnd reminders"), (driver.as_str(), "rmnd merge-driver %O %A %B %P")]
+            .iter()
+            .all(|(key, value)| matches!(self.git(&["config", key, value]), Some(output) if output.status.success()))
+    }
+
+    fn git(&self, args: &[&str]) -> Option<std::process::Output> {
+        Command::new("git").arg("-C").arg(&self.work_tree).args(args).output().ok()
+    }
+
+    pub(crate) fn tracking(&self, file: &Path) -> Tracking {
+        let Some(relative) = self.relative(file) else {
+            return Tracking::NotInRepo;
+        };
+
+        match self.git(&["ls-files", "--error-unmatch", "--", &relative]) {
+            | Some(output) if output.status.success() => return Tracking::Tracked,
+            | None => return Tracking::NotInRepo,
+            | _ => {},
+        }
+
+        match self.git(&["check-ignore", "-q", "--", &relative]) {
+            | Some(output) if output.status.success() => Tracking::Ignored,
+            | _ => Tracking::Untracked,
+        }
+    }
+
+    /// Adds an anchored pattern for `file` to `.git/info/exclude`, or to the `.gitignore` at the root of the working
+    /// tree. Returns the file that was written to, or `None` if the pattern was already there.
+    pub(crate) fn ignore(&self, file: &Path, gitignore: bool) -> Option<PathBuf> {
+        let pattern = format!("/{}", self.relative(file)?);
+        let target = if gitignore {
+            self.work_tree.join(".gitignore")
+        } else {
+            self.common_dir.join("info").join("exclude")
+        };
+
+        let mut contents = fs::read_to_string(&target).unwrap_or_default();
+        if contents.lines().any(|v| v.trim() == pattern) {
+            return None;
+        }
+
+        if !contents.is_empty() && !contents.ends_with('\n') {
+            contents.push('\n');
+        }
+        contents.push_str(&pattern);
+        contents.push('\n');
+
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).ok()?;
+        }
+        fs::write(&target, contents).ok()?;
+        Some(target)
+    }
+}
+
+/// The branch checked out in the repository containing `dir`, `None` outside of a repository or on a detached HEAD.
+pub(crate) fn current_branch(dir: &Path) -> Option<String> {
+    match Repo::find(dir)?.head()? {
+        | Head::Branch(branch) => Some(branch),
+        | Head::Detached => None,
+    }
+}
+
+/// Whether `branch` matches a reminder's branch condition, a glob such as `feature/*`.
+pub(crate) fn branch_matches(pattern: &str, branch: &str) -> bool {
+    match glob::Pattern::new(pattern) {
+        | Ok(glob) => glob.matches(branch),
+        | Err(_) => pattern == branch,
+    }
+}
+
+/// Convenience for `Repo::find` followed by `Repo::tracking`.
+pub(crate) fn tracking(file: &Path) -> Tracking {
+    match file.parent().and_then(Repo::find) {
+        | Some(repo) => repo.tracking(file),
+        | None => Tracking::NotInRepo,
+    }
+}
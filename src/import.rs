@@ -0,0 +1,490 @@
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use regex::Regex;
+use serde_json::Value;
+
+/// The formats `rmnd import --from` reads.
+pub(crate) const FORMATS: [&str; 5] = ["todotxt", "taskwarrior", "org", "markdown", "ics"];
+
+/// A task read from another tool, before it is mapped onto a `Reminder`.
+pub(crate) struct Item {
+    pub(crate) text: String,
+    /// The priority as the tool writes it, e.g. `A` for todo.txt or `H` for Taskwarrior, looked up in
+    /// `settings.import_priorities`.
+    pub(crate) priority: Option<String>,
+    /// The position of the priority from most to least important, used when it is not in the table.
+    pub(crate) rank: Option<usize>,
+    /// Projects as `+project` and contexts or tags as `@tag`, like todo.txt writes them.
+    pub(crate) tags: Vec<String>,
+    pub(crate) due: Option<NaiveDate>,
+    /// Set for completed tasks, to when they were completed if the tool records it.
+    pub(crate) done: Option<Option<DateTime<Local>>>,
+    /// Hidden until then, for calendar events that remind of something at a time.
+    pub(crate) snoozed_until: Option<DateTime<Local>>,
+    /// Identifies the task across imports, so importing it again updates the reminder.
+    pub(crate) uid: Option<String>,
+}
+
+impl Item {
+    fn new(text: String) -> Self {
+        Self { text, priority: None, rank: None, tags: vec![], due: None, done: None, snoozed_until: None, uid: None }
+    }
+}
+
+/// Reads every task in `contents`, which is in one of `FORMATS`.
+pub(crate) fn parse(format: &str, contents: &str) -> Result<Vec<Item>, String> {
+    match format {
+        "todotxt" => Ok(contents.lines().filter_map(todo_txt).collect()),
+        "taskwarrior" => taskwarrior(contents),
+        "org" => Ok(org(contents)),
+        "markdown" => Ok(markdown(contents)),
+        "ics" => ics(contents),
+        _ => Err(format!("Unknown format {}, expected one of {}.", format, FORMATS.join(", "))),
+    }
+}
+
+fn date(text: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(text, "%Y-%m-%d").ok()
+}
+
+/// Midnight of `date` in the local time zone.
+pub(crate) fn local_midnight(date: NaiveDate) -> Option<DateTime<Local>> {
+    Local.from_local_datetime(&date.and_time(NaiveTime::MIN)).earliest()
+}
+
+/// The rank of a single letter priority, `A` being the most important.
+fn letter_rank(letter: &str) -> Option<usize> {
+    let mut chars = letter.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_uppercase() => Some(c as usize - 'A' as usize),
+        _ => None,
+    }
+}
+
+/// A line of todo.txt: `x 2024-01-02 2024-01-01 (A) Call mom +family @phone due:2024-01-05`, where `x` and the
+/// completion date mark it done, the second date is when it was created and `pri:A` keeps the priority of done tasks.
+fn todo_txt(line: &str) -> Option<Item> {
+    let mut rest = line.trim();
+    if rest.is_empty() {
+        return None;
+    }
+
+    let mut done = None;
+    if let Some(after) = rest.strip_prefix("x ") {
+        rest = after.trim_start();
+        let completed = rest.split_whitespace().next().and_then(date);
+        if completed.is_some() {
+            rest = rest.split_once(' ').map(|v| v.1).unwrap_or("").trim_start();
+        }
+        done = Some(completed.and_then(local_midnight));
+    }
+
+    let mut priority = None;
+    if let Some(after) = rest.strip_prefix('(') {
+        let mut chars = after.chars();
+        if let (Some(letter), Some(text)) = (chars.next(), chars.as_str().strip_prefix(") ")) {
+            if letter_rank(&letter.to_string()).is_some() {
+                priority = Some(letter.to_string());
+                rest = text.trim_start();
+            }
+        }
+    }
+
+    // The creation date is not kept.
+    if rest.split_whitespace().next().and_then(date).is_some() {
+        rest = rest.split_once(' ').map(|v| v.1).unwrap_or("").trim_start();
+    }
+
+    let mut item = Item::new(String::new());
+    let mut words = vec![];
+    for word in rest.split_whitespace() {
+        if (word.starts_with('+') || word.starts_with('@')) && word.len() > 1 {
+            item.tags.push(word.to_string());
+        } else if let Some(due) = word.strip_prefix("due:").and_then(date) {
+            item.due = Some(due);
+        } else if let Some(letter) = word.strip_prefix("pri:").filter(|v| letter_rank(v).is_some()) {
+            priority = Some(letter.to_string());
+        } else {
+            words.push(word);
+        }
+    }
+
+    item.text = words.join(" ");
+    if item.text.is_empty() {
+        return None;
+    }
+    item.rank = priority.as_deref().and_then(letter_rank);
+    item.priority = priority;
+    item.done = done;
+    Some(item)
+}
+
+/// The output of `task export`, a JSON array of tasks. Deleted tasks and recurring templates are skipped.
+fn taskwarrior(contents: &str) -> Result<Vec<Item>, String> {
+    let tasks: Vec<Value> = serde_json::from_str(contents).map_err(|e| format!("Invalid Taskwarrior export: {}", e))?;
+    let timestamp = |value: &Value| {
+        let text = value.as_str()?;
+        let time = NaiveDateTime::parse_from_str(text, "%Y%m%dT%H%M%SZ").ok()?;
+        Some(Utc.from_utc_datetime(&time).with_timezone(&Local))
+    };
+
+    let mut items = vec![];
+    for task in tasks {
+        let status = task["status"].as_str().unwrap_or("pending");
+        if status == "deleted" || status == "recurring" {
+            continue;
+        }
+        let Some(description) = task["description"].as_str() else {
+            continue;
+        };
+
+        let mut item = Item::new(description.to_string());
+        if let Some(priority) = task["priority"].as_str() {
+            item.rank = ["H", "M", "L"].iter().position(|v| *v == priority);
+            item.priority = Some(priority.to_string());
+        }
+        if let Some(project) = task["project"].as_str() {
+            item.tags.push(format!("+{}", project));
+        }
+        for tag in task["tags"].as_array().into_iter().flatten().filter_map(|v| v.as_str()) {
+            item.tags.push(format!("@{}", tag));
+        }
+        item.due = timestamp(&task["due"]).map(|v| v.date_naive());
+        if status == "completed" {
+            item.done = Some(timestamp(&task["end"]));
+        }
+        items.push(item);
+    }
+    Ok(items)
+}
+
+/// Headlines with a todo keyword, `** TODO [#A] Title :work:home:`, followed by an optional planning line with
+/// `DEADLINE: <2024-01-05 Fri>`, `SCHEDULED:` or `CLOSED: [2024-01-02 Tue 10:00]`. DONE and CANCELLED headlines are
+/// imported as done.
+fn org(contents: &str) -> Vec<Item> {
+    let headline = Regex::new(r"^\*+\s+(TODO|NEXT|WAITING|DONE|CANCELLED|CANCELED)\s+(?:\[#([A-Z])\]\s+)?(.*?)\s*(?::((?:[^\s:]+:)+))?\s*$").unwrap();
+    let planning = Regex::new(r"(DEADLINE|SCHEDULED|CLOSED):\s*[<\[](\d{4}-\d{2}-\d{2})").unwrap();
+
+    let mut items: Vec<Item> = vec![];
+    let mut planned = false;
+    for line in contents.lines() {
+        if let Some(captures) = headline.captures(line) {
+            let mut item = Item::new(captures[3].to_string());
+            if let Some(priority) = captures.get(2) {
+                item.priority = Some(priority.as_str().to_string());
+                item.rank = letter_rank(priority.as_str());
+            }
+            if let Some(tags) = captures.get(4) {
+                item.tags = tags.as_str().split(':').filter(|v| !v.is_empty()).map(|v| format!("@{}", v)).collect();
+            }
+            if matches!(&captures[1], "DONE" | "CANCELLED" | "CANCELED") {
+                item.done = Some(None);
+            }
+            items.push(item);
+            planned = false;
+            continue;
+        }
+
+        // Only the line right below a headline holds its planning.
+        let Some(item) = items.last_mut().filter(|_| !planned) else {
+            continue;
+        };
+        planned = true;
+        for captures in planning.captures_iter(line) {
+            let date = date(&captures[2]);
+            match &captures[1] {
+                "DEADLINE" => item.due = date,
+                "SCHEDULED" if item.due.is_none() => item.due = date,
+                "CLOSED" if item.done.is_some() => item.done = Some(date.and_then(local_midnight)),
+                _ => {},
+            }
+        }
+    }
+    items
+}
+
+/// Checklist items, `- [ ] Write docs` or `* [x] Ship it`, in any list and at any depth. A `due:2024-01-05` or
+/// `📅 2024-01-05` in the text sets the due date.
+fn markdown(contents: &str) -> Vec<Item> {
+    let checkbox = Regex::new(r"^\s*(?:[-*+]|\d+[.)])\s+\[([ xX])\]\s+(.+?)\s*$").unwrap();
+    let due = Regex::new(r"(?:due:|📅\s*)(\d{4}-\d{2}-\d{2})").unwrap();
+
+    contents
+        .lines()
+        .filter_map(|line| {
+            let captures = checkbox.captures(line)?;
+            let text = &captures[2];
+            let mut item = Item::new(due.replace_all(text, "").split_whitespace().collect::<Vec<_>>().join(" "));
+            item.due = due.captures(text).and_then(|v| date(&v[1]));
+            if &captures[1] != " " {
+                item.done = Some(None);
+            }
+            Some(item).filter(|v| !v.text.is_empty())
+        })
+        .collect()
+}
+
+/// Reverses the escaping of an iCalendar text value.
+fn unescape_ics(text: &str) -> String {
+    let mut result = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => result.push('\n'),
+            Some(c) => result.push(c),
+            None => {},
+        }
+    }
+    result
+}
+
+/// Splits a text list at the commas that are not escaped.
+fn split_ics_list(text: &str) -> Vec<String> {
+    let mut items = vec![];
+    let mut current = String::new();
+    let mut escaped = false;
+    for c in text.chars() {
+        if c == ',' && !escaped {
+            items.push(unescape_ics(&current));
+            current.clear();
+            continue;
+        }
+        escaped = c == '\\' && !escaped;
+        current.push(c);
+    }
+    items.push(unescape_ics(&current));
+    items.into_iter().map(|v| v.trim().to_string()).filter(|v| !v.is_empty()).collect()
+}
+
+/// A date or time value, `20240105`, `20240105T120000Z` in UTC or `20240105T120000` in local time. Times with a `TZID`
+/// are read as local time.
+fn ics_time(value: &str) -> Option<DateTime<Local>> {
+    if let Some(utc) = value.strip_suffix('Z') {
+        let time = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+        return Some(Utc.from_utc_datetime(&time).with_timezone(&Local));
+    }
+    if let Ok(time) = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S") {
+        return Local.from_local_datetime(&time).earliest();
+    }
+    NaiveDate::parse_from_str(value, "%Y%m%d").ok().and_then(local_midnight)
+}
+
+/// The properties of a calendar component by name, without their parameters.
+struct Component {
+    kind: String,
+    properties: Vec<(String, String)>,
+    has_alarm: bool,
+}
+
+impl Component {
+    fn get(&self, name: &str) -> Option<&str> {
+        self.properties.iter().find(|v| v.0 == name).map(|v| v.1.as_str())
+    }
+}
+
+/// The todos of an iCalendar file and the events with an alarm, which become reminders snoozed until they start.
+/// Priorities 1 to 4 are high, 5 is medium and 6 to 9 are low as the format defines them, `import_priorities` can map
+/// each number. Categories become tags.
+fn ics(contents: &str) -> Result<Vec<Item>, String> {
+    // Long lines are folded onto lines starting with a space or tab.
+    let mut lines: Vec<String> = vec![];
+    for line in contents.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    if lines.first().map(|v| v.trim()) != Some("BEGIN:VCALENDAR") {
+        return Err("Invalid iCalendar file, expected it to start with BEGIN:VCALENDAR.".to_string());
+    }
+
+    let mut components = vec![];
+    let mut stack: Vec<Component> = vec![];
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let name = name.split(';').next().unwrap().to_ascii_uppercase();
+        match name.as_str() {
+            "BEGIN" => stack.push(Component { kind: value.trim().to_ascii_uppercase(), properties: vec![], has_alarm: false }),
+            "END" => {
+                let Some(component) = stack.pop() else {
+                    continue;
+                };
+                if component.kind == "VALARM" {
+                    if let Some(parent) = stack.last_mut() {
+                        parent.has_alarm = true;
+                    }
+                } else if component.kind == "VTODO" || (component.kind == "VEVENT" && component.has_alarm) {
+                    components.push(component);
+                }
+            },
+            _ => {
+                if let Some(component) = stack.last_mut() {
+                    component.properties.push((name, value.to_string()));
+                }
+            },
+        }
+    }
+
+    let mut items = vec![];
+    for component in components {
+        let Some(summary) = component.get("SUMMARY").map(unescape_ics).filter(|v| !v.trim().is_empty()) else {
+            continue;
+        };
+
+        let mut item = Item::new(summary.trim().to_string());
+        item.uid = component.get("UID").map(|v| v.trim().to_string());
+        if let Some(priority) = component.get("PRIORITY").and_then(|v| v.trim().parse::<u8>().ok()).filter(|v| (1..=9).contains(v)) {
+            item.rank = Some(match priority {
+                1..=4 => 0,
+                5 => 1,
+                _ => 2,
+            });
+            item.priority = Some(priority.to_string());
+        }
+        for categories in component.properties.iter().filter(|v| v.0 == "CATEGORIES") {
+            for category in split_ics_list(&categories.1) {
+                item.tags.push(if category.starts_with(['+', '@']) { category } else { format!("@{}", category) });
+            }
+        }
+
+        if component.kind == "VTODO" {
+            item.due = component.get("DUE").and_then(ics_time).map(|v| v.date_naive());
+            let status = component.get("STATUS").unwrap_or("").trim().to_ascii_uppercase();
+            if status == "COMPLETED" || status == "CANCELLED" || component.get("COMPLETED").is_some() {
+                item.done = Some(component.get("COMPLETED").and_then(ics_time));
+            }
+        } else {
+            item.snoozed_until = component.get("DTSTART").and_then(ics_time);
+        }
+        items.push(item);
+    }
+    Ok(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn todo_txt_line() {
+        let item = todo_txt("(A) 2024-01-01 Call mom +family @phone due:2024-01-05").unwrap();
+        assert_eq!(item.text, "Call mom");
+        assert_eq!(item.priority.as_deref(), Some("A"));
+        assert_eq!(item.rank, Some(0));
+        assert_eq!(item.tags, ["+family", "@phone"]);
+        assert_eq!(item.due, date("2024-01-05"));
+        assert!(item.done.is_none());
+    }
+
+    #[test]
+    fn todo_txt_done() {
+        let item = todo_txt("x 2024-01-02 2024-01-01 Ship it pri:B").unwrap();
+        assert_eq!(item.text, "Ship it");
+        assert_eq!(item.priority.as_deref(), Some("B"));
+        assert_eq!(item.done, Some(date("2024-01-02").and_then(local_midnight)));
+    }
+
+    #[test]
+    fn todo_txt_without_priority() {
+        let item = todo_txt("(é) hello world").unwrap();
+        assert_eq!(item.text, "(é) hello world");
+        assert!(item.priority.is_none());
+        assert_eq!(todo_txt("(a) lower case").unwrap().text, "(a) lower case");
+        assert_eq!(todo_txt("(B)").unwrap().text, "(B)");
+        assert!(todo_txt("   ").is_none());
+    }
+
+    #[test]
+    fn org_headlines() {
+        let items = org("* TODO [#A] Write docs :work:home:\nDEADLINE: <2024-01-05 Fri>\n** DONE Ship it\nCLOSED: [2024-01-02 Tue 10:00]\n* Notes\n");
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].text, "Write docs");
+        assert_eq!(items[0].priority.as_deref(), Some("A"));
+        assert_eq!(items[0].tags, ["@work", "@home"]);
+        assert_eq!(items[0].due, date("2024-01-05"));
+        assert_eq!(items[1].text, "Ship it");
+        assert_eq!(items[1].done, Some(date("2024-01-02").and_then(local_midnight)));
+    }
+
+    #[test]
+    fn markdown_checkboxes() {
+        let items = markdown("# List\n- [ ] Write docs due:2024-01-05\n  * [x] Ship it\n1. [ ] 📅 2024-02-01 Plan\n- plain item\n");
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0].text, "Write docs");
+        assert_eq!(items[0].due, date("2024-01-05"));
+        assert!(items[0].done.is_none());
+        assert_eq!(items[1].text, "Ship it");
+        assert_eq!(items[1].done, Some(None));
+        assert_eq!(items[2].text, "Plan");
+        assert_eq!(items[2].due, date("2024-02-01"));
+    }
+
+    #[test]
+    fn ics_todos_and_events() {
+        let contents = [
+            "BEGIN:VCALENDAR",
+            "BEGIN:VTODO",
+            "UID:1@example.com",
+            "SUMMARY:Call mom\\, then dad\\nsoon",
+            "PRIORITY:2",
+            "CATEGORIES:family,+home,a\\,b",
+            "DUE;VALUE=DATE:20240105",
+            "END:VTODO",
+            "BEGIN:VTODO",
+            "SUMMARY:A very long",
+            "  summary",
+            "PRIORITY:5",
+            "STATUS:COMPLETED",
+            "COMPLETED:20240102T100000Z",
+            "END:VTODO",
+            "BEGIN:VTODO",
+            "SUMMARY:Cancelled",
+            "PRIORITY:9",
+            "STATUS:CANCELLED",
+            "END:VTODO",
+            "BEGIN:VEVENT",
+            "SUMMARY:No alarm",
+            "END:VEVENT",
+            "BEGIN:VEVENT",
+            "SUMMARY:Meeting",
+            "DTSTART:20240105T120000",
+            "BEGIN:VALARM",
+            "TRIGGER:-PT15M",
+            "END:VALARM",
+            "END:VEVENT",
+            "END:VCALENDAR",
+        ]
+        .join("\r\n");
+        let items = ics(&contents).unwrap();
+        assert_eq!(items.len(), 4);
+
+        assert_eq!(items[0].text, "Call mom, then dad\nsoon");
+        assert_eq!(items[0].uid.as_deref(), Some("1@example.com"));
+        assert_eq!((items[0].priority.as_deref(), items[0].rank), (Some("2"), Some(0)));
+        assert_eq!(items[0].tags, ["@family", "+home", "@a,b"]);
+        assert_eq!(items[0].due, date("2024-01-05"));
+        assert!(items[0].done.is_none());
+
+        assert_eq!(items[1].text, "A very long summary");
+        assert_eq!(items[1].rank, Some(1));
+        let completed = Utc.with_ymd_and_hms(2024, 1, 2, 10, 0, 0).unwrap().with_timezone(&Local);
+        assert_eq!(items[1].done, Some(Some(completed)));
+
+        assert_eq!(items[2].rank, Some(2));
+        assert_eq!(items[2].done, Some(None));
+
+        assert_eq!(items[3].text, "Meeting");
+        let start = NaiveDateTime::parse_from_str("20240105T120000", "%Y%m%dT%H%M%S").unwrap();
+        assert_eq!(items[3].snoozed_until, Local.from_local_datetime(&start).earliest());
+    }
+
+    #[test]
+    fn ics_requires_calendar() {
+        assert!(ics("BEGIN:VTODO\nEND:VTODO\n").is_err());
+    }
+}
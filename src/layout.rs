@@ -0,0 +1,358 @@
+use toml_edit::{ArrayOfTables, DocumentMut, Item, Table, Value};
+
+/// Applies `updated`, a freshly serialized config, onto `existing`, the text of the file it replaces, so that comments,
+/// key order and formatting of entries that did not change are kept and a diff only shows what did. Returns `None` if
+/// either does not parse.
+pub(crate) fn update(existing: &str, updated: &str,) -> Option<String,> {
+    let mut document = existing.parse::<DocumentMut>().ok()?;
+    let mut updated = updated.parse::<DocumentMut>().ok()?;
+
+    // Files written by hand may leave out `config_paths` and `settings`, they are not added while empty.
+    let omitted: Vec<String,> = updated
+        .iter()
+        .filter(|(key, item,)| !document.contains_key(key,) && is_empty(item,),)
+        .map(|(key, _,)| key.to_string(),)
+        .collect();
+    for key in omitted {
+        updated.remove(&key,);
+    }
+    if prefers_double_quotes(document.as_table(),) {
+        requote_table(updated.as_table_mut(),);
+    }
+
+    update_table(document.as_table_mut(), updated.as_table(),);
+    Some(document.to_string(),)
+}
+
+fn is_empty(item: &Item,) -> bool {
+    match item {
+        | Item::Table(table,) => table.is_empty(),
+        | Item::ArrayOfTables(array,) => array.is_empty(),
+        | Item::Value(Value::Array(array,),) => array.is_empty(),
+        | Item::Value(Value::InlineTable(table,),) => table.is_empty(),
+        | _ => false,
+    }
+}
+
+/// Whether most strings of the document are written in double quotes. The serializer writes single quoted literal
+/// strings, values written into such a document are quoted like the rest of it.
+fn prefers_double_quotes(table: &Table,) -> bool {
+    fn count(item: &Item, counts: &mut (usize, usize,),) {
+        match item {
+            | Item::Table(table,) => table.iter().for_each(|(_, v,)| count(v, counts,),),
+            | Item::ArrayOfTables(array,) => array
+                .iter()
+                .flat_map(|v| v.iter(),)
+                .for_each(|(_, v,)| count(v, counts,),),
+            | Item::Value(value,) => count_value(value, counts,),
+            | Item::None => {},
+        }
+    }
+    fn count_value(value: &Value, counts: &mut (usize, usize,),) {
+        match value {
+            | Value::String(string,) => match string.as_repr().and_then(|v| v.as_raw().as_str(),) {
+                | Some(raw,) if raw.starts_with('"',) => counts.0 += 1,
+                | Some(raw,) if raw.starts_with('\'',) => counts.1 += 1,
+                | _ => {},
+            },
+            | Value::Array(array,) => array.iter().for_each(|v| count_value(v, counts,),),
+            | Value::InlineTable(table,) => table.iter().for_each(|(_, v,)| count_value(v, counts,),),
+            | _ => {},
+        }
+    }
+
+    let mut counts = (0, 0,);
+    table.iter().for_each(|(_, v,)| count(v, &mut counts,),);
+    counts.0 > counts.1
+}
+
+fn requote_table(table: &mut Table,) {
+    for (_, item,) in table.iter_mut() {
+        match item {
+            | Item::Table(table,) => requote_table(table,),
+            | Item::ArrayOfTables(array,) => array.iter_mut().for_each(requote_table,),
+            | Item::Value(value,) => requote(value,),
+            | Item::None => {},
+        }
+    }
+}
+
+/// Drops the single quotes the serializer wrote strings in.
+fn requote(value: &mut Value,) {
+    match value {
+        | Value::String(string,) => {
+            // Without a representation toml_edit picks double quotes unless the string holds some.
+            let mut quoted = toml_edit::Formatted::new(string.value().clone(),);
+            *quoted.decor_mut() = string.decor().clone();
+            *string = quoted;
+        },
+        | Value::Array(array,) => array.iter_mut().for_each(requote,),
+        | Value::InlineTable(table,) => table.iter_mut().for_each(|(_, v,)| requote(v,),),
+        | _ => {},
+    }
+}
+
+fn update_table(old: &mut Table, new: &Table,) {
+    let removed: Vec<String,> = old
+        .iter()
+        .map(|(key, _,)| key.to_string(),)
+        .filter(|key| !new.contains_key(key,),)
+        .collect();
+    for key in removed {
+        old.remove(&key,);
+    }
+
+    for (key, item,) in new.iter() {
+        match old.get_mut(key,) {
+            | Some(existing,) => update_item(existing, item,),
+            | None => {
+                old.insert(key, unpositioned(item,),);
+            },
+        }
+    }
+}
+
+fn update_item(old: &mut Item, new: &Item,) {
+    match (&mut *old, new,) {
+        | (Item::Table(old,), Item::Table(new,),) => update_table(old, new,),
+        | (Item::ArrayOfTables(old,), Item::ArrayOfTables(new,),) => update_array(old, new,),
+        | (Item::Value(old,), Item::Value(new,),) => update_value(old, new.clone(),),
+        // Written inline by hand, keep it inline.
+        | (Item::Value(old @ Value::InlineTable(_,),), Item::Table(new,),) => {
+            update_value(old, Value::InlineTable(new.clone().into_inline_table(),),)
+        },
+        | (Item::Value(old @ Value::Array(_,),), Item::ArrayOfTables(new,),) => {
+            update_value(old, Value::Array(new.clone().into_array(),),)
+        },
+        | _ => *old = unpositioned(new,),
+    }
+}
+
+/// Replaces a value that changed, keeping the whitespace and comment around it.
+fn update_value(old: &mut Value, mut new: Value,) {
+    if same_value(old, &new,) {
+        return;
+    }
+    *new.decor_mut() = old.decor().clone();
+    *old = new;
+}
+
+/// Compares values by what they hold rather than how they are written.
+fn same_value(a: &Value, b: &Value,) -> bool {
+    let parse = |value: &Value| toml::from_str::<toml::Value,>(&format!("v = {}", value.to_string().trim()),).ok();
+    match (parse(a,), parse(b,),) {
+        | (Some(a,), Some(b,),) => a == b,
+        | _ => false,
+    }
+}
+
+/// Whether two entries of an array of tables are the same reminder, priority, checklist or pathless context. Reminders
+/// written before ids existed are matched by their text.
+fn same_entry(old: &Table, new: &Table,) -> bool {
+    let text = |table: &Table, key: &str| table.get(key,).and_then(|v| v.as_str(),).map(|v| v.to_string(),);
+    for key in ["id", "name", "directory",] {
+        if let (Some(a,), Some(b,),) = (text(old, key,), text(new, key,),) {
+            return a == b;
+        }
+    }
+    text(old, "text",).is_some() && text(old, "text",) == text(new, "text",)
+}
+
+fn update_array(old: &mut ArrayOfTables, new: &ArrayOfTables,) {
+    let mut remaining: Vec<Option<Table,>,> = old.iter().cloned().map(Some,).collect();
+    let mut tables = vec![];
+    for table in new.iter() {
+        let matching = remaining
+            .iter()
+            .position(|v| v.as_ref().map(|v| same_entry(v, table,),).unwrap_or(false,),);
+        match matching.and_then(|index| remaining[index].take(),) {
+            | Some(mut existing,) => {
+                let table = without_derived_id(&existing, table,);
+                update_table(&mut existing, &table,);
+                tables.push(existing,);
+            },
+            | None => tables.push(unpositioned_table(table,),),
+        }
+    }
+
+    // Tables are written in the order of their position in the file, a moved entry takes the position of the one
+    // before it so that the new order is kept. Added tables have none and follow the table before them.
+    let mut last = 0;
+    for table in &mut tables {
+        if let Some(position,) = table.position() {
+            if position < last {
+                raise_positions(table, last,);
+            } else {
+                last = position;
+            }
+        }
+    }
+
+    old.clear();
+    for table in tables {
+        old.push(table,);
+    }
+}
+
+/// `new` without its id if `old` was written without one and nothing else changed. Such reminders derive the same id
+/// every time they are read, see `Reminder::derive_id`, it is only written once the reminder is edited.
+fn without_derived_id(old: &Table, new: &Table,) -> Table {
+    let mut stripped = new.clone();
+    if old.contains_key("id",) || stripped.remove("id",).is_none() {
+        return new.clone();
+    }
+
+    let mut updated = old.clone();
+    update_table(&mut updated, &stripped,);
+    if updated.to_string() == old.to_string() {
+        stripped
+    } else {
+        new.clone()
+    }
+}
+
+fn raise_positions(table: &mut Table, minimum: usize,) {
+    if table.position().map(|v| v < minimum,).unwrap_or(false,) {
+        table.set_position(minimum,);
+    }
+    for (_, item,) in table.iter_mut() {
+        match item {
+            | Item::Table(table,) => raise_positions(table, minimum,),
+            | Item::ArrayOfTables(array,) => {
+                for table in array.iter_mut() {
+                    raise_positions(table, minimum,);
+                }
+            },
+            | _ => {},
+        }
+    }
+}
+
+/// A copy of an item from the freshly serialized document without the positions it had there, so it is written after
+/// the table it was inserted behind.
+fn unpositioned(item: &Item,) -> Item {
+    match item {
+        | Item::Table(table,) => Item::Table(unpositioned_table(table,),),
+        | Item::ArrayOfTables(array,) => {
+            let mut copy = ArrayOfTables::new();
+            for table in array.iter() {
+                copy.push(unpositioned_table(table,),);
+            }
+            Item::ArrayOfTables(copy,)
+        },
+        | item => item.clone(),
+    }
+}
+
+fn unpositioned_table(table: &Table,) -> Table {
+    let mut copy = Table::new();
+    copy.set_implicit(table.is_implicit(),);
+    for (key, item,) in table.iter() {
+        copy.insert(key, unpositioned(item,),);
+    }
+    copy
+}
+
+#[cfg(test)]
+mod tests {
+    use super::update;
+
+    const EXISTING: &str = r#"# My reminders
+[[reminders]]
+id = "a"
+priority = "Low"
+text = "first" # keep this
+
+# The second one
+[[reminders]]
+id = "b"
+priority = "Low"
+text = "second"
+"#;
+
+    #[test]
+    fn unchanged_document_is_kept() {
+        let updated = "[[reminders]]\nid = 'a'\npriority = 'Low'\ntext = 'first'\n\n[[reminders]]\nid = 'b'\npriority \
+                       = 'Low'\ntext = 'second'\n";
+        assert_eq!(update(EXISTING, updated).unwrap(), EXISTING);
+    }
+
+    #[test]
+    fn changed_value_keeps_comment() {
+        let updated = "[[reminders]]\nid = 'a'\npriority = 'High'\ntext = 'first'\n\n[[reminders]]\nid = \
+                       'b'\npriority = 'Low'\ntext = 'second'\n";
+        let result = update(EXISTING, updated,).unwrap();
+        assert_eq!(
+            result,
+            EXISTING.replacen(r#"priority = "Low""#, r#"priority = "High""#, 1)
+        );
+    }
+
+    #[test]
+    fn added_entry_is_appended_in_double_quotes() {
+        let updated = "[[reminders]]\nid = 'a'\npriority = 'Low'\ntext = 'first'\n\n[[reminders]]\nid = 'b'\npriority \
+                       = 'Low'\ntext = 'second'\n\n[[reminders]]\nid = 'c'\npriority = 'Low'\ntext = 'third'\n";
+        let result = update(EXISTING, updated,).unwrap();
+        assert_eq!(
+            result,
+            format!(
+                "{}\n[[reminders]]\nid = \"c\"\npriority = \"Low\"\ntext = \"third\"\n",
+                EXISTING
+            )
+        );
+    }
+
+    #[test]
+    fn single_quoted_document_stays_single_quoted() {
+        let existing = "[[reminders]]\nid = 'a'\ntext = 'first'\n";
+        let updated = "[[reminders]]\nid = 'a'\ntext = 'first'\n\n[[reminders]]\nid = 'b'\ntext = 'second'\n";
+        assert_eq!(update(existing, updated).unwrap(), updated);
+    }
+
+    #[test]
+    fn removed_entry_is_dropped() {
+        let updated = "[[reminders]]\nid = 'b'\npriority = 'Low'\ntext = 'second'\n";
+        let result = update(EXISTING, updated,).unwrap();
+        assert!(!result.contains("first"));
+        assert!(result.contains("# The second one\n[[reminders]]\nid = \"b\""));
+    }
+
+    #[test]
+    fn reordered_entries_keep_their_comments() {
+        let updated = "[[reminders]]\nid = 'b'\npriority = 'Low'\ntext = 'second'\n\n[[reminders]]\nid = \
+                       'a'\npriority = 'Low'\ntext = 'first'\n";
+        let result = update(EXISTING, updated,).unwrap();
+        let second = result.find("text = \"second\"",).unwrap();
+        let first = result.find("text = \"first\" # keep this",).unwrap();
+        assert!(second < first, "{}", result);
+    }
+
+    #[test]
+    fn empty_keys_are_not_added() {
+        let updated = "config_paths = []\n\n[[reminders]]\nid = 'a'\npriority = 'Low'\ntext = \
+                       'first'\n\n[[reminders]]\nid = 'b'\npriority = 'Low'\ntext = 'second'\n\n[settings]\n";
+        assert_eq!(update(EXISTING, updated).unwrap(), EXISTING);
+    }
+
+    #[test]
+    fn derived_ids_are_only_written_for_edited_entries() {
+        let existing = "[[reminders]]\npriority = \"Low\"\ntext = \"first\"\n\n[[reminders]]\npriority = \
+                        \"Low\"\ntext = \"second\"\n";
+        let updated = "[[reminders]]\nid = 'a'\npriority = 'Low'\ntext = 'first'\n\n[[reminders]]\nid = 'b'\npriority \
+                       = 'High'\ntext = 'second'\n";
+        let result = update(existing, updated,).unwrap();
+        assert_eq!(
+            result,
+            existing.replace(
+                "priority = \"Low\"\ntext = \"second\"",
+                "priority = \"High\"\ntext = \"second\"\nid = \"b\""
+            )
+        );
+    }
+
+    #[test]
+    fn invalid_documents_are_rejected() {
+        assert!(update("a = [", EXISTING).is_none());
+        assert!(update(EXISTING, "a = [").is_none());
+    }
+}
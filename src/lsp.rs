@@ -0,0 +1,416 @@
+use std::{
+    collections::HashMap,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+};
+
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::{
+    CodeAction,
+    CodeActionKind,
+    CodeActionParams,
+    CodeActionProviderCapability,
+    Command,
+    Diagnostic,
+    DiagnosticSeverity,
+    DidChangeTextDocumentParams,
+    DidCloseTextDocumentParams,
+    DidOpenTextDocumentParams,
+    ExecuteCommandOptions,
+    ExecuteCommandParams,
+    Position,
+    PublishDiagnosticsParams,
+    Range,
+    SaveOptions,
+    ServerCapabilities,
+    TextDocumentSyncCapability,
+    TextDocumentSyncKind,
+    TextDocumentSyncOptions,
+    Url,
+};
+use serde_json::{json, Value};
+
+use crate::{
+    checklist,
+    conditions,
+    config_file::{ConfigSum, Format},
+    reminder::{Color, LocalPriority, Reminder, Severity},
+};
+
+const SOURCE: &str = "rmnd";
+const DONE: &str = "rmnd.done";
+const SNOOZE: &str = "rmnd.snooze";
+
+/// The snoozes offered as code actions, as `(title, duration)`.
+const SNOOZES: [(&str, &str); 2] = [("a day", "1d"), ("a week", "1w")];
+
+/// The open documents, diagnostics of rmnd.toml files are computed from the editor's buffer rather than the file.
+struct Server {
+    connection: Connection,
+    documents: HashMap<Url, String>,
+}
+
+/// Runs a language server over stdio until the editor shuts it down. Every open file gets the active reminders of its
+/// contexts as diagnostics on its first line, and rmnd.toml files are additionally validated.
+pub(crate) fn run() {
+    let (connection, io_threads) = Connection::stdio();
+
+    let capabilities = ServerCapabilities {
+        // Saves are asked for so that saving a rmnd.toml refreshes the diagnostics of the other open files.
+        text_document_sync: Some(TextDocumentSyncCapability::Options(TextDocumentSyncOptions {
+            open_close: Some(true),
+            change: Some(TextDocumentSyncKind::FULL),
+            save: Some(SaveOptions::default().into()),
+            ..Default::default()
+        })),
+        code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+        execute_command_provider: Some(ExecuteCommandOptions {
+            commands: vec![DONE.to_string(), SNOOZE.to_string()],
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    if let Err(error) = connection.initialize(serde_json::to_value(capabilities).unwrap()) {
+        eprintln!("rmnd lsp: failed to initialize: {}", error);
+        return;
+    }
+
+    let receiver = connection.receiver.clone();
+    let mut server = Server { connection, documents: HashMap::new() };
+
+    for message in &receiver {
+        match message {
+            Message::Request(request) => {
+                if server.connection.handle_shutdown(&request).unwrap_or(true) {
+                    break;
+                }
+                // Loading configs panics on broken files, which should fail the request rather than the server.
+                let id = request.id.clone();
+                let response = panic::catch_unwind(AssertUnwindSafe(|| server.request(request)))
+                    .unwrap_or_else(|_| Response::new_err(id, ErrorCode::InternalError as i32, "rmnd panicked, see the server log.".to_string()));
+                server.send(Message::Response(response));
+            },
+            Message::Notification(notification) => {
+                if panic::catch_unwind(AssertUnwindSafe(|| server.notification(notification))).is_err() {
+                    eprintln!("rmnd lsp: failed to handle a notification");
+                }
+            },
+            Message::Response(_) => {},
+        }
+    }
+
+    drop(server);
+    io_threads.join().ok();
+}
+
+impl Server {
+    fn send(&self, message: Message) {
+        self.connection.sender.send(message).ok();
+    }
+
+    fn request(&mut self, request: Request) -> Response {
+        match request.method.as_str() {
+            "textDocument/codeAction" => match serde_json::from_value::<CodeActionParams>(request.params) {
+                Ok(params) => Response::new_ok(request.id, code_actions(params)),
+                Err(error) => Response::new_err(request.id, ErrorCode::InvalidParams as i32, error.to_string()),
+            },
+            "workspace/executeCommand" => {
+                let result = serde_json::from_value::<ExecuteCommandParams>(request.params)
+                    .map_err(|e| e.to_string())
+                    .and_then(execute);
+                match result {
+                    Ok(()) => {
+                        self.publish_all();
+                        Response::new_ok(request.id, Value::Null)
+                    },
+                    Err(error) => Response::new_err(request.id, ErrorCode::InvalidParams as i32, error),
+                }
+            },
+            _ => Response::new_err(request.id, ErrorCode::MethodNotFound as i32, format!("Unsupported request {}.", request.method)),
+        }
+    }
+
+    fn notification(&mut self, notification: Notification) {
+        match notification.method.as_str() {
+            "textDocument/didOpen" => {
+                let Ok(params) = serde_json::from_value::<DidOpenTextDocumentParams>(notification.params) else {
+                    return;
+                };
+                self.documents.insert(params.text_document.uri.clone(), params.text_document.text);
+                self.publish(&params.text_document.uri);
+            },
+            "textDocument/didChange" => {
+                let Ok(params) = serde_json::from_value::<DidChangeTextDocumentParams>(notification.params) else {
+                    return;
+                };
+                let Some(change) = params.content_changes.into_iter().last() else {
+                    return;
+                };
+                let uri = params.text_document.uri;
+                self.documents.insert(uri.clone(), change.text);
+                // Other files only depend on what is saved, and their conditions may run commands.
+                if config_path(&uri).is_some() {
+                    self.publish(&uri);
+                }
+            },
+            // A saved rmnd.toml can change the reminders of every open file.
+            "textDocument/didSave" => self.publish_all(),
+            "textDocument/didClose" => {
+                let Ok(params) = serde_json::from_value::<DidCloseTextDocumentParams>(notification.params) else {
+                    return;
+                };
+                self.documents.remove(&params.text_document.uri);
+                self.send_diagnostics(params.text_document.uri, vec![]);
+            },
+            _ => {},
+        }
+    }
+
+    fn publish_all(&self) {
+        for uri in self.documents.keys() {
+            self.publish(uri);
+        }
+    }
+
+    fn publish(&self, uri: &Url) {
+        let Ok(path) = uri.to_file_path() else {
+            return;
+        };
+        let path = path.canonicalize().unwrap_or(path);
+
+        let mut diagnostics = vec![];
+        if let (Some(path), Some(text)) = (config_path(uri), self.documents.get(uri)) {
+            diagnostics.extend(validate(&path, text));
+        }
+        diagnostics.extend(reminder_diagnostics(&path, self.documents.get(uri).map(String::as_str).unwrap_or_default()));
+
+        self.send_diagnostics(uri.clone(), diagnostics);
+    }
+
+    fn send_diagnostics(&self, uri: Url, diagnostics: Vec<Diagnostic>) {
+        let params = PublishDiagnosticsParams { uri, diagnostics, version: None };
+        self.send(Message::Notification(Notification::new("textDocument/publishDiagnostics".to_string(), params)));
+    }
+}
+
+/// The canonical path of a document that is a context file or the global config.
+fn config_path(uri: &Url) -> Option<PathBuf> {
+    let path = uri.to_file_path().ok()?;
+    let path = path.canonicalize().unwrap_or(path);
+    if crate::is_config_name(path.file_name()?) || path == crate::global_config_path() {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+fn severity(severity: Severity) -> DiagnosticSeverity {
+    match severity {
+        Severity::Hint => DiagnosticSeverity::HINT,
+        Severity::Info => DiagnosticSeverity::INFORMATION,
+        Severity::Warning => DiagnosticSeverity::WARNING,
+        Severity::Error => DiagnosticSeverity::ERROR,
+    }
+}
+
+/// The active reminders of the contexts containing `path`, global reminders are left to `rmnd show`. Blocking
+/// reminders are at least warnings. Reminders defined in the document itself, whose `text` is given, are placed on
+/// their entry and all others at its start.
+fn reminder_diagnostics(path: &Path, text: &str) -> Vec<Diagnostic> {
+    let Some(dir) = path.parent() else {
+        return vec![];
+    };
+    let sum = crate::get_local_for(dir);
+    let mut scope = crate::Scope::new(&sum.global_path, path.to_path_buf());
+
+    sum.reminders
+        .iter()
+        .filter(|v| v.path != sum.global_path)
+        .filter(|v| scope.is_active(v))
+        .map(|v| {
+            let mut level = match sum.resolve_priority(&v.reminder.priority, &v.path) {
+                Some(priority) => priority.severity(),
+                None => Severity::Info,
+            };
+            if v.reminder.blocking && level == Severity::Info {
+                level = Severity::Warning;
+            }
+
+            let range = match &v.defined_at {
+                Some((file, line)) if file == path => line_range(text, *line as u32 - 1),
+                _ => Range::default(),
+            };
+
+            Diagnostic {
+                range,
+                severity: Some(severity(level)),
+                source: Some(SOURCE.to_string()),
+                message: format!("{}: {}", v.reminder.priority, v.reminder.text),
+                data: Some(json!({ "path": v.path, "id": v.reminder.id, "text": v.reminder.text })),
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+/// Offers to mark done or snooze the reminders behind the diagnostics at the cursor.
+fn code_actions(params: CodeActionParams) -> Vec<CodeAction> {
+    let mut actions = vec![];
+    for diagnostic in params.context.diagnostics {
+        if diagnostic.source.as_deref() != Some(SOURCE) {
+            continue;
+        }
+        let Some(data) = &diagnostic.data else {
+            continue;
+        };
+        let (Some(path), Some(id), Some(text)) = (data["path"].as_str(), data["id"].as_str(), data["text"].as_str()) else {
+            continue;
+        };
+
+        let action = |title: String, command: &str, mut arguments: Vec<Value>| {
+            arguments.splice(0..0, [json!(path), json!(id)]);
+            CodeAction {
+                title: title.clone(),
+                kind: Some(CodeActionKind::QUICKFIX),
+                diagnostics: Some(vec![diagnostic.clone()]),
+                command: Some(Command { title, command: command.to_string(), arguments: Some(arguments) }),
+                ..Default::default()
+            }
+        };
+
+        actions.push(action(format!("Mark done: {}", text), DONE, vec![]));
+        for (title, duration) in SNOOZES {
+            actions.push(action(format!("Snooze for {}: {}", title, text), SNOOZE, vec![json!(duration)]));
+        }
+    }
+    actions
+}
+
+/// Runs one of the commands behind the code actions, both take the context path and the reminder's id.
+fn execute(params: ExecuteCommandParams) -> Result<(), String> {
+    let arguments: Vec<&str> = params.arguments.iter().filter_map(|v| v.as_str()).collect();
+
+    match (params.command.as_str(), arguments.as_slice()) {
+        (DONE, [path, id]) => update(path, id, |v| v.done_at = Some(checklist::now())),
+        (SNOOZE, [path, id, duration]) => {
+            let duration = chrono::Duration::from_std(conditions::parse_duration(duration)?).map_err(|e| e.to_string())?;
+            update(path, id, |v| v.snoozed_until = Some(checklist::now() + duration))
+        },
+        _ => Err(format!("Unknown command {} or wrong arguments.", params.command)),
+    }
+}
+
+fn update(path: &str, id: &str, change: impl FnOnce(&mut Reminder)) -> Result<(), String> {
+    let mut config = crate::load_context(Path::new(path));
+    let Some(reminder) = config.reminders.iter_mut().find(|v| v.id == id && v.done_at.is_none()) else {
+        return Err(format!("No reminder {} in {}, it may have been changed or removed since.", id, path));
+    };
+    change(reminder);
+    crate::save_config(&config);
+    Ok(())
+}
+
+/// The lines of each `[[name]]` table in a toml document, in order.
+fn table_lines(text: &str, name: &str) -> Vec<u32> {
+    let header = format!("[[{}]]", name);
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| line.trim() == header)
+        .map(|(index, _)| index as u32)
+        .collect()
+}
+
+/// The line of `key` within the table starting at `start`, or `start` itself if the key is not found.
+fn key_line(text: &str, start: u32, key: &str) -> u32 {
+    for (index, line) in text.lines().enumerate().skip(start as usize + 1) {
+        let line = line.trim();
+        if line.starts_with("[[") {
+            break;
+        }
+        let is_key = line.strip_prefix(key).map(|v| v.trim_start().starts_with('=')).unwrap_or(false);
+        // `[priorities.color.TrueColor]` holds a true color.
+        if is_key || (line.starts_with('[') && line.contains(&format!(".{}", key))) {
+            return index as u32;
+        }
+    }
+    start
+}
+
+/// The whole of the zero based `line` of `text`.
+fn line_range(text: &str, line: u32) -> Range {
+    let length = text.lines().nth(line as usize).map(|v| v.encode_utf16().count()).unwrap_or(0);
+    Range::new(Position::new(line, 0), Position::new(line, length as u32))
+}
+
+fn line_diagnostic(text: &str, line: u32, severity: DiagnosticSeverity, message: String) -> Diagnostic {
+    Diagnostic {
+        range: line_range(text, line),
+        severity: Some(severity),
+        source: Some(SOURCE.to_string()),
+        message,
+        ..Default::default()
+    }
+}
+
+/// Checks an unsaved context file or global config: syntax, colors rmnd does not know and reminders whose priority is
+/// not defined along their context chain. Only toml files get diagnostics on the lines they are about.
+fn validate(path: &Path, text: &str) -> Vec<Diagnostic> {
+    let format = Format::of(path);
+    let mut diagnostics = if format == Format::Toml { validate_toml(text) } else { vec![] };
+    if !diagnostics.is_empty() {
+        return diagnostics;
+    }
+
+    let config = match format.parse(text.as_bytes()) {
+        Ok(config) => config,
+        Err(error) => {
+            let line = error.line.map(|v| v as u32 - 1).unwrap_or(0);
+            return vec![line_diagnostic(text, line, DiagnosticSeverity::ERROR, error.message)];
+        },
+    };
+
+    // Resolve priorities against the buffer rather than what is saved.
+    let global_path = crate::global_config_path();
+    let mut sum = if *path == global_path { ConfigSum::new(global_path) } else { crate::get_local_for(path.parent().unwrap()) };
+    sum.priorities.retain(|v| v.path != path);
+    for priority in config.priorities {
+        sum.priorities.push(LocalPriority { priority, path: path.to_path_buf() });
+    }
+
+    let reminder_lines = table_lines(text, "reminders");
+    for (index, reminder) in config.reminders.iter().enumerate() {
+        if sum.resolve_priority(&reminder.priority, path).is_none() {
+            let line = reminder_lines.get(index).map(|v| key_line(text, *v, "priority")).unwrap_or(0);
+            let message = format!("Unknown priority {}, it is not defined here or in an enclosing context.", reminder.priority);
+            diagnostics.push(line_diagnostic(text, line, DiagnosticSeverity::WARNING, message));
+        }
+    }
+
+    diagnostics
+}
+
+/// The toml syntax and colors rmnd does not know, located on the line of the offending `color`.
+fn validate_toml(text: &str) -> Vec<Diagnostic> {
+    let value = match text.parse::<toml::Value>() {
+        Ok(value) => value,
+        Err(error) => {
+            let line = error.line_col().map(|(line, _)| line as u32).unwrap_or(0);
+            return vec![line_diagnostic(text, line, DiagnosticSeverity::ERROR, error.to_string())];
+        },
+    };
+
+    let mut diagnostics = vec![];
+    let priority_lines = table_lines(text, "priorities");
+    let priorities = value.get("priorities").and_then(|v| v.as_array()).cloned().unwrap_or_default();
+    for (index, priority) in priorities.into_iter().enumerate() {
+        let Some(color) = priority.get("color") else {
+            continue;
+        };
+        if color.clone().try_into::<Color>().is_err() {
+            let line = priority_lines.get(index).map(|v| key_line(text, *v, "color")).unwrap_or(0);
+            let message = format!("Unknown color {}, expected a name such as Red or BrightBlue, or a TrueColor table with r, g and b.", color);
+            diagnostics.push(line_diagnostic(text, line, DiagnosticSeverity::ERROR, message));
+        }
+    }
+    diagnostics
+}
@@ -1,21 +1,40 @@
-#![feature(slice_group_by)]
 
 mod reminder;
 mod config_file;
+mod merge;
+mod git;
+mod checklist;
+mod conditions;
+mod scan;
+mod lsp;
+mod tui;
+mod picker;
+mod bulk_edit;
+mod import;
+mod export;
+mod layout;
+#[cfg(unix)]
+mod daemon;
 
 use core::panic;
-use std::{ffi::OsString, fs, path::{Path, PathBuf}, env::current_dir};
+use std::{collections::HashMap, ffi::OsString, fs, path::{Path, PathBuf}, env::current_dir};
+
+use chrono::{DateTime, Local, NaiveDate, NaiveTime};
 
 use clap::{builder::NonEmptyStringValueParser, Arg, ArgAction, ArgMatches, Command};
 use colored::Colorize;
-use config_file::ConfigSum;
-use reminder::{LocalReminder, Reminder, Priority, Color};
-use text_io::read;
+use config_file::{ConfigSum, Format, PathlessContext};
+use reminder::{LocalPriority, LocalReminder, Reminder, Priority};
+use text_io::try_read;
 
-use crate::config_file::ConfigFile;
+use crate::{checklist::{Checklist, ChecklistRun, CheckedStep}, config_file::ConfigFile};
 
 const CONFIG_DIR: &str = "/Users/maxrink/.config/";
 const CONFIG_NAME: &str = r"rmnd.toml";
+/// The names a local context file may have, in the order they are looked for, see `config_file::Format`.
+const CONFIG_NAMES: [&str; 5] = ["rmnd.toml", "rmnd.yaml", "rmnd.yml", "rmnd.json", "rmnd.json5"];
+/// Where copies of each local context are kept, so a re-cloned rmnd.toml can be merged with what this machine knew.
+const KNOWN_DIR: &str = r"rmnd/known";
 
 /// This function defines the structure of the command, the help descriptions, and
 /// some parameter validation. No other command functionality is defined here.
@@ -30,20 +49,22 @@ fn cli() -> Command<'static> {
             Command::new("show")
                 .alias("s")
                 .about("Show reminders for the current context.")
+                .args(selection_args())
                 .arg(
-                    Arg::new("all")
-                        .long("--all")
-                        .short('a')
-                        .action(ArgAction::SetTrue)
-                        .takes_value(false)
-                        .help("Show all reminders, including all contextual reminders across the entire system."),
+                    Arg::new("format")
+                        .long("--format")
+                        .action(ArgAction::Set)
+                        .value_parser(["human", "quickfix"])
+                        .conflicts_with("explain")
+                        .help("How to print the reminders, quickfix prints `path:line:col: priority: text` lines for editors' problem matchers."),
                 )
                 .arg(
-                    Arg::new("priorities")
-                        .long("--priorities")
-                        .short('p')
-                        .action(ArgAction::Set)
-                        .help("Show reminders with specific priorities. Takes a comma delimited list of priorities by name or id. (surround in quotes)"),
+                    Arg::new("explain")
+                        .long("--explain")
+                        .short('x')
+                        .action(ArgAction::SetTrue)
+                        .takes_value(false)
+                        .help("Also list hidden reminders, with the result of each branch and --when condition."),
                 )
                 .arg(
                     Arg::new("show-ids")
@@ -72,6 +93,13 @@ fn cli() -> Command<'static> {
                       .action(ArgAction::Set)
                       .value_parser(NonEmptyStringValueParser::new())
               )
+              .arg(
+                  Arg::new("priority")
+                      .short('p')
+                      .action(ArgAction::Set)
+                      .required(false)
+              )
+              .args(reminder_args())
               .subcommand(
                   Command::new("reminder")
                       .about("Add a reminder.")
@@ -89,6 +117,7 @@ fn cli() -> Command<'static> {
                               .action(ArgAction::Set)
                               .value_parser(NonEmptyStringValueParser::new())
                       )
+                      .args(reminder_args())
               )
               .subcommand(
                   Command::new("priority")
@@ -108,6 +137,7 @@ fn cli() -> Command<'static> {
                                .required(false)
                                .action(ArgAction::Set)
                                .value_parser(NonEmptyStringValueParser::new())
+                               .help("The color reminders with this priority are shown in, e.g. red, \"bright blue\"."),
                        )
               )
         )
@@ -165,18 +195,32 @@ fn cli() -> Command<'static> {
                                 .takes_value(true)
                                 .help("The reminder to be modified, id or name."),
                         )
+                        .arg(
+                            Arg::new("text")
+                                .long("--text")
+                                .short('t')
+                                .action(ArgAction::Set)
+                                .value_parser(NonEmptyStringValueParser::new())
+                                .help("The new text, asked for when neither this nor a priority is given."),
+                        )
+                        .arg(
+                            Arg::new("priority")
+                                .short('p')
+                                .action(ArgAction::Set)
+                                .help("The new priority."),
+                        )
                 )
         )
-        // Remove UNSTARTED HERE DOWN
+        // Remove
         .subcommand(
             Command::new("remove")
                 .alias("r")
-                .about("Remove a reminder, priority or context.")
-                .arg_required_else_help(true)
-                .arg(Arg::new("id"))
-                .arg(Arg::new("priority"))
-                .arg(Arg::new("reminder"))
-                .arg(Arg::new("context"))
+                .about("Remove a reminder of the current contexts or a global one, picked from a list when omitted or ambiguous.")
+                .arg(
+                    Arg::new("reminder")
+                        .action(ArgAction::Set)
+                        .help("The reminder to be removed, id or text."),
+                )
         )
         // Priorities
         .subcommand(
@@ -189,19 +233,545 @@ fn cli() -> Command<'static> {
         .subcommand(
             Command::new("init")
                 .about("Initialize a local contextual reminder file in this directory.")
+                .arg(
+                    Arg::new("no-file")
+                        .long("--no-file")
+                        .action(ArgAction::SetTrue)
+                        .takes_value(false)
+                        .help("Store this directory's context in the global config instead of a rmnd.toml in the directory."),
+                )
+                .arg(
+                    Arg::new("format")
+                        .long("--format")
+                        .action(ArgAction::Set)
+                        .value_parser(Format::NAMES)
+                        .conflicts_with("no-file")
+                        .help("The format of a new context file, rmnd.toml by default, rmnd.yaml, rmnd.json or rmnd.json5."),
+                )
+                .arg(
+                    Arg::new("private")
+                        .long("--private")
+                        .action(ArgAction::SetTrue)
+                        .takes_value(false)
+                        .conflicts_with_all(&["shared", "no-file"])
+                        .help("Keep the rmnd.toml out of git by adding it to .git/info/exclude."),
+                )
+                .arg(
+                    Arg::new("gitignore")
+                        .long("--gitignore")
+                        .action(ArgAction::SetTrue)
+                        .takes_value(false)
+                        .conflicts_with_all(&["shared", "no-file"])
+                        .help("Keep the rmnd.toml out of git by adding it to the repository's .gitignore, implies --private."),
+                )
+                .arg(
+                    Arg::new("shared")
+                        .long("--shared")
+                        .action(ArgAction::SetTrue)
+                        .takes_value(false)
+                        .conflicts_with("no-file")
+                        .help("Leave the rmnd.toml to be tracked by git so it is shared with the repository."),
+                )
+        )
+        // Context
+        .subcommand(
+            Command::new("context")
+                .alias("ctx")
+                .about("Manage the registered contextual reminder files.")
+                .subcommand_required(true)
+                .arg_required_else_help(true)
+                .subcommand(
+                    Command::new("list")
+                        .alias("ls")
+                        .about("List registered contexts with their reminder counts."),
+                )
+                .subcommand(
+                    Command::new("add")
+                        .about("Register an existing rmnd.toml, defaults to the current directory.")
+                        .arg(
+                            Arg::new("path")
+                                .action(ArgAction::Set)
+                                .value_parser(NonEmptyStringValueParser::new())
+                                .help("The directory or file of the context."),
+                        ),
+                )
+                .subcommand(
+                    Command::new("remove")
+                        .alias("rm")
+                        .about("Unregister a context, the file itself is left untouched.")
+                        .arg(
+                            Arg::new("path")
+                                .required(true)
+                                .action(ArgAction::Set)
+                                .value_parser(NonEmptyStringValueParser::new())
+                                .help("The directory or file of the context."),
+                        ),
+                )
+                .subcommand(
+                    Command::new("mv")
+                        .alias("move")
+                        .about("Point a registered context at the directory it was moved to.")
+                        .arg(
+                            Arg::new("from")
+                                .required(true)
+                                .action(ArgAction::Set)
+                                .value_parser(NonEmptyStringValueParser::new())
+                                .help("The old directory or file of the context."),
+                        )
+                        .arg(
+                            Arg::new("to")
+                                .required(true)
+                                .action(ArgAction::Set)
+                                .value_parser(NonEmptyStringValueParser::new())
+                                .help("The new directory or file of the context."),
+                        ),
+                )
+                .subcommand(
+                    Command::new("prune")
+                        .about("Unregister every context whose file no longer exists."),
+                )
+        )
+        // Git
+        .subcommand(
+            Command::new("git")
+                .about("Integrate reminders with git.")
+                .subcommand_required(true)
+                .arg_required_else_help(true)
+                .subcommand(
+                    Command::new("install-hooks")
+                        .about("Install pre-commit, pre-push and post-checkout hooks into the current repository. post-checkout shows reminders, pre-commit and pre-push show reminders added with --event and fail while any of them is --blocking, bypass them with `git commit --no-verify` or `git push --no-verify`.")
+                        .arg(
+                            Arg::new("force")
+                                .long("--force")
+                                .short('f')
+                                .action(ArgAction::SetTrue)
+                                .takes_value(false)
+                                .help("Replace existing hooks that were not installed by rmnd."),
+                        ),
+                )
+                .subcommand(
+                    Command::new("install-merge-driver")
+                        .about("Merge context files by reminder id instead of by line, so teammates adding reminders on different branches do not conflict. Writes .gitattributes, which should be committed, and the repository's git config, which every clone has to set up."),
+                )
+                .subcommand(
+                    Command::new("hook")
+                        .about("Run by the installed git hooks.")
+                        .hide(true)
+                        .arg(Arg::new("event").required(true).value_parser(git::HOOKS))
+                        .arg(Arg::new("args").multiple_values(true)),
+                )
+        )
+        // Merge driver
+        .subcommand(
+            Command::new("merge-driver")
+                .about("A git merge driver for context files, see `rmnd git install-merge-driver`. Reminders, priorities and checklists are merged by id or name, field by field. Fields both branches changed keep the current branch's value and fail the merge so they can be checked. Falls back to git's line-based merge if a version does not parse.")
+                .arg(Arg::new("base").required(true).help("The common ancestor, %O."))
+                .arg(Arg::new("current").required(true).help("The current branch's version, %A, the result is written here."))
+                .arg(Arg::new("other").required(true).help("The other branch's version, %B."))
+                .arg(Arg::new("path").help("The path of the merged file, %P, its extension decides the format.")),
+        )
+        // Exec
+        .subcommand(
+            Command::new("exec")
+                .about("Show reminders bound to a command, added with --command, then run it and exit with its exit code.")
+                .arg_required_else_help(true)
+                .arg(
+                    Arg::new("yes")
+                        .long("--yes")
+                        .short('y')
+                        .action(ArgAction::SetTrue)
+                        .takes_value(false)
+                        .help("Do not ask for confirmation, even for reminders added with --confirm."),
+                )
+                .arg(
+                    Arg::new("check")
+                        .long("--check")
+                        .action(ArgAction::SetTrue)
+                        .takes_value(false)
+                        .help("Only show the matching reminders without running the command, used by --shell-hook."),
+                )
+                .arg(
+                    Arg::new("shell-hook")
+                        .long("--shell-hook")
+                        .action(ArgAction::Set)
+                        .value_parser(["bash", "zsh"])
+                        .exclusive(true)
+                        .help("Print a preexec hook that shows matching reminders for every command typed in the shell."),
+                )
+                .arg(
+                    Arg::new("command")
+                        .multiple_values(true)
+                        .allow_hyphen_values(true)
+                        .last(true)
+                        .value_parser(clap::builder::OsStringValueParser::new())
+                        .help("The command to run, after --."),
+                )
+        )
+        // Checklist
+        .subcommand(
+            Command::new("checklist")
+                .alias("cl")
+                .about("Manage ordered checklists, e.g. for release procedures, stored in the current context.")
+                .subcommand_required(true)
+                .arg_required_else_help(true)
+                .arg(
+                    Arg::new("global")
+                        .long("--global")
+                        .short('g')
+                        .action(ArgAction::SetTrue)
+                        .takes_value(false)
+                        .global(true)
+                        .help("Use the checklists of the global context."),
+                )
+                .subcommand(
+                    Command::new("add")
+                        .about("Create a checklist, or append steps to an existing one.")
+                        .arg(Arg::new("name").required(true).value_parser(NonEmptyStringValueParser::new()))
+                        .arg(
+                            Arg::new("steps")
+                                .required(true)
+                                .multiple_values(true)
+                                .value_parser(NonEmptyStringValueParser::new())
+                                .help("The steps in order."),
+                        ),
+                )
+                .subcommand(
+                    Command::new("run")
+                        .about("Walk through a checklist step by step, resuming an unfinished run.")
+                        .arg(Arg::new("name").required(true).value_parser(NonEmptyStringValueParser::new()))
+                        .arg(
+                            Arg::new("restart")
+                                .long("--restart")
+                                .action(ArgAction::SetTrue)
+                                .takes_value(false)
+                                .help("Abandon an unfinished run and start over."),
+                        ),
+                )
+                .subcommand(
+                    Command::new("list")
+                        .alias("ls")
+                        .about("List checklists with the progress of their latest run.")
+                        .arg(Arg::new("name").help("Show every step and who checked it for this checklist.")),
+                )
+                .subcommand(
+                    Command::new("remove")
+                        .alias("rm")
+                        .about("Remove a checklist along with its runs.")
+                        .arg(Arg::new("name").required(true)),
+                )
+        )
+        // Scan
+        .subcommand(
+            Command::new("scan")
+                .about("Find marker comments such as TODO, FIXME and REMIND(tag) in the files of the current contexts, respecting .gitignore. Markers are set with `markers` and mapped to priorities with `[settings.marker_priorities]` in a rmnd.toml.")
+                .arg(
+                    Arg::new("all")
+                        .long("--all")
+                        .short('a')
+                        .action(ArgAction::SetTrue)
+                        .takes_value(false)
+                        .help("Scan every registered context instead of those containing the current directory."),
+                )
+                .arg(
+                    Arg::new("import")
+                        .long("--import")
+                        .action(ArgAction::SetTrue)
+                        .takes_value(false)
+                        .help("Add the found markers to their context's rmnd.toml as reminders, skipping ones already there."),
+                )
+        )
+        // Edit all
+        .subcommand(
+            Command::new("edit-all")
+                .about("Edit every reminder of a context at once in $EDITOR, one `id priority text` line each. Lines can be edited, reordered, added with - as the id or deleted.")
+                .args(context_args())
+        )
+        // Import
+        .subcommand(
+            Command::new("import")
+                .about("Import tasks from another tool into a context, skipping those whose text is already there. Calendar entries are matched by UID and updated when imported again. Priorities are mapped with `[settings.import_priorities]`, e.g. A = \"Critical\" or 1 = \"Critical\", and otherwise by their order.")
+                .arg(
+                    Arg::new("from")
+                        .long("--from")
+                        .action(ArgAction::Set)
+                        .required(true)
+                        .value_parser(import::FORMATS)
+                        .help("The format of the file."),
+                )
+                .arg(
+                    Arg::new("file")
+                        .required(true)
+                        .action(ArgAction::Set)
+                        .value_parser(NonEmptyStringValueParser::new())
+                        .help("The file to import, e.g. todo.txt or the output of `task export`."),
+                )
+                .arg(
+                    Arg::new("map")
+                        .long("--map")
+                        .short('m')
+                        .action(ArgAction::Append)
+                        .value_parser(NonEmptyStringValueParser::new())
+                        .help("Map a priority of the file to one of ours, e.g. --map A=Critical, taking precedence over the settings. May be repeated."),
+                )
+                .arg(
+                    Arg::new("priority")
+                        .short('p')
+                        .action(ArgAction::Set)
+                        .help("The priority of tasks without one, defaults to the least important one defined."),
+                )
+                .arg(
+                    Arg::new("dry-run")
+                        .long("--dry-run")
+                        .short('n')
+                        .action(ArgAction::SetTrue)
+                        .takes_value(false)
+                        .help("Only show what would be imported."),
+                )
+                .args(context_args())
+        )
+        // Export
+        .subcommand(
+            Command::new("export")
+                .about("Export the reminders `show` would show, and snoozed ones, to another tool's format. The .ics calendar only holds reminders with a due date, as todos, and snoozed ones, as events for when they come back.")
+                .arg(
+                    Arg::new("to")
+                        .long("--to")
+                        .action(ArgAction::Set)
+                        .required(true)
+                        .value_parser(export::FORMATS)
+                        .help("The format to write, html is a self-contained page."),
+                )
+                .arg(
+                    Arg::new("output")
+                        .long("--output")
+                        .short('o')
+                        .action(ArgAction::Set)
+                        .value_parser(NonEmptyStringValueParser::new())
+                        .help("Write to this file instead of standard output."),
+                )
+                .arg(
+                    Arg::new("done")
+                        .long("--done")
+                        .short('d')
+                        .action(ArgAction::SetTrue)
+                        .takes_value(false)
+                        .help("Also export reminders that are done."),
+                )
+                .args(selection_args())
+        )
+        // Convert
+        .subcommand(
+            Command::new("convert")
+                .about("Rewrite a context file in another format, e.g. rmnd.toml as rmnd.yaml, and register it under its new name. Saving keeps a file's format.")
+                .arg(
+                    Arg::new("to")
+                        .long("--to")
+                        .action(ArgAction::Set)
+                        .required(true)
+                        .value_parser(Format::NAMES)
+                        .help("The format to convert to."),
+                )
+                .args(context_args())
+        )
+        // Daemon
+        .subcommand(
+            Command::new("daemon")
+                .about("Run in the foreground and notify about reminders when their --due day, --remind-at time or --every interval comes. Every context is watched and reloaded when it changes. Only available on Unix.")
+                .args_conflicts_with_subcommands(true)
+                .arg(
+                    Arg::new("notify")
+                        .long("--notify")
+                        .action(ArgAction::Set)
+                        .value_parser(NonEmptyStringValueParser::new())
+                        .help("The shell command to run when a reminder fires, overriding the notifier setting. It gets RMND_ID, RMND_TEXT, RMND_PRIORITY, RMND_AUTHOR, RMND_CONTEXT, RMND_DUE, RMND_REMIND_AT, RMND_EVERY, RMND_TAGS and RMND_REASON."),
+                )
+                .arg(
+                    Arg::new("due-time")
+                        .long("--due-time")
+                        .action(ArgAction::Set)
+                        .value_parser(|v: &str| NaiveTime::parse_from_str(v, "%H:%M").map_err(|_| format!("Invalid time of day {}, expected HH:MM.", v)))
+                        .help("The time of day due reminders fire at, 09:00 by default."),
+                )
+                .subcommand(Command::new("status").about("Show whether the daemon is running and what it will notify about next.")),
+        )
+        // Tui
+        .subcommand(
+            Command::new("tui")
+                .about("Browse and edit the reminders of the current contexts in a full-screen interface.")
+                .arg(
+                    Arg::new("all")
+                        .long("--all")
+                        .short('a')
+                        .action(ArgAction::SetTrue)
+                        .takes_value(false)
+                        .help("Include every registered context."),
+                )
+        )
+        // Lsp
+        .subcommand(
+            Command::new("lsp")
+                .about("Run a language server over stdio, showing the reminders of a file's contexts as diagnostics with code actions to mark them done or snooze them, and validating rmnd.toml files.")
+        )
+        // Check
+        .subcommand(
+            Command::new("check")
+                .about("List reminders across all contexts that reference an undefined priority, optionally remapping them.")
+                .arg(
+                    Arg::new("map")
+                        .long("--map")
+                        .short('m')
+                        .action(ArgAction::Append)
+                        .value_parser(NonEmptyStringValueParser::new())
+                        .help("Remap reminders with an undefined priority to an existing one, formatted as old=new. May be repeated."),
+                )
+                .arg(
+                    Arg::new("interactive")
+                        .long("--interactive")
+                        .short('i')
+                        .action(ArgAction::SetTrue)
+                        .takes_value(false)
+                        .help("Prompt for a replacement for each undefined priority."),
+                )
         )
 }
 
+/// The arguments selecting which reminders `show` and `export` work on.
+fn selection_args() -> Vec<Arg<'static>> {
+    vec![
+        Arg::new("all")
+            .long("--all")
+            .short('a')
+            .action(ArgAction::SetTrue)
+            .takes_value(false)
+            .help("Show all reminders, including all contextual reminders across the entire system."),
+        Arg::new("priorities")
+            .long("--priorities")
+            .short('p')
+            .action(ArgAction::Set)
+            .help("Show reminders with specific priorities. Takes a comma delimited list of priorities by name or id. (surround in quotes)"),
+        Arg::new("for-file")
+            .long("--for-file")
+            .short('f')
+            .action(ArgAction::Set)
+            .value_parser(NonEmptyStringValueParser::new())
+            .conflicts_with("all")
+            .help("Show the reminders relevant to a file, including those anchored to it, e.g. for an editor."),
+        Arg::new("scan")
+            .long("--scan")
+            .action(ArgAction::SetTrue)
+            .takes_value(false)
+            .help("Include marker comments such as TODO and FIXME found in the contexts' files, see `rmnd scan`."),
+    ]
+}
+
+/// Optional reminder fields shared by `add <reminder>` and `add reminder`.
+fn reminder_args() -> Vec<Arg<'static>> {
+    vec![
+        Arg::new("branch")
+            .long("--branch")
+            .short('b')
+            .action(ArgAction::Set)
+            .min_values(0)
+            .require_equals(true)
+            .help("Only show the reminder while a branch matching this glob is checked out (--branch=feature/*), defaults to the current branch."),
+        Arg::new("event")
+            .long("--event")
+            .short('e')
+            .action(ArgAction::Append)
+            .value_parser(["pre-commit", "pre-push"])
+            .help("Show the reminder when this git hook runs, see `rmnd git install-hooks`. May be repeated."),
+        Arg::new("blocking")
+            .long("--blocking")
+            .action(ArgAction::SetTrue)
+            .takes_value(false)
+            .requires("event")
+            .help("Fail the git hooks given by --event while this reminder exists."),
+        Arg::new("command")
+            .long("--command")
+            .action(ArgAction::Set)
+            .value_parser(NonEmptyStringValueParser::new())
+            .help("Show the reminder when `rmnd exec` runs a command line matching this glob, e.g. \"cargo publish*\"."),
+        Arg::new("confirm")
+            .long("--confirm")
+            .action(ArgAction::SetTrue)
+            .takes_value(false)
+            .requires("command")
+            .help("Ask for confirmation before `rmnd exec` runs the matching command."),
+        Arg::new("anchor")
+            .long("--anchor")
+            .action(ArgAction::Set)
+            .value_parser(NonEmptyStringValueParser::new())
+            .help("Only show the reminder within this path or glob, e.g. \"migrations/**\", relative paths are taken from the current directory."),
+        Arg::new("when")
+            .long("--when")
+            .short('w')
+            .action(ArgAction::Append)
+            .value_parser(|v: &str| v.parse::<conditions::Condition>().map(|_| v.to_string()))
+            .help("Only show the reminder while this condition holds: env:NAME[=VALUE|~REGEX], exists:PATH, newer:PATH:AGE (e.g. 7d) or cmd[=STATUS][@TIMEOUT]:COMMAND, prefix with ! to negate. May be repeated."),
+        Arg::new("due")
+            .long("--due")
+            .action(ArgAction::Set)
+            .value_parser(|v: &str| NaiveDate::parse_from_str(v, "%Y-%m-%d").map_err(|_| format!("Invalid date {}, expected YYYY-MM-DD.", v)))
+            .help("The day the reminder is due, e.g. 2024-01-05. `rmnd daemon` notifies about it that day."),
+        Arg::new("remind-at")
+            .long("--remind-at")
+            .action(ArgAction::Set)
+            .value_parser(conditions::parse_time)
+            .help("When `rmnd daemon` notifies about the reminder: \"YYYY-MM-DD HH:MM\", HH:MM for the next time of day or a duration from now such as 30m."),
+        Arg::new("every")
+            .long("--every")
+            .action(ArgAction::Set)
+            .value_parser(|v: &str| conditions::parse_duration(v).map(|_| v.to_string()))
+            .help("Repeat the notification at this interval from --remind-at or the due day, e.g. 1d or 2w."),
+    ]
+}
+
+/// The arguments of commands that work on one context, the most local one unless another is chosen.
+fn context_args() -> Vec<Arg<'static>> {
+    vec![
+        Arg::new("global")
+            .long("--global")
+            .short('g')
+            .action(ArgAction::SetTrue)
+            .takes_value(false)
+            .help("Use the global config."),
+        Arg::new("context")
+            .long("--context")
+            .short('c')
+            .action(ArgAction::Set)
+            .value_parser(NonEmptyStringValueParser::new())
+            .conflicts_with("global")
+            .help("Use this context, a directory or rmnd.toml, instead of the most local one."),
+    ]
+}
+
+/// Whether a file name is one of `CONFIG_NAMES`.
+fn is_config_name(name: &std::ffi::OsStr) -> bool {
+    CONFIG_NAMES.iter().any(|v| name == *v)
+}
+
+/// The context file in `dir`, in whichever format it is stored.
+fn config_in(dir: &Path) -> Option<PathBuf> {
+    CONFIG_NAMES.iter().map(|v| dir.join(v)).find(|v| v.is_file())
+}
+
 fn read_config(path: &PathBuf) -> ConfigFile {
     // println!("Attempting to read: {:?}", path);
     match fs::read(path) {
         Ok(bytes) => {
-            let loaded = toml::from_slice::<ConfigFile>(&bytes);
-            if let Ok(mut success) = loaded {
-                success.path = Some(path.clone());
-                return success;
-            } else {
-                panic!("Failed to read the global config file, it may be corrupted.");
+            let format = Format::of(path);
+            let loaded = format.parse(&bytes);
+            match loaded {
+                Ok(mut success) => {
+                    success.path = Some(path.clone());
+                    // Lines are only known for toml, other formats point at the top of the file.
+                    if format == Format::Toml {
+                        success.read_lines(&bytes);
+                    }
+                    success.assign_ids();
+                    success
+                },
+                Err(error) => panic!("Failed to read {:?}, it may be corrupted: {}", path, error.message),
             }
         },
         Err(error) => {
@@ -210,39 +780,92 @@ fn read_config(path: &PathBuf) -> ConfigFile {
     }
 }
 
+fn warn(message: &str) {
+    eprintln!("{} {}", "warning:".yellow(), message);
+}
+
+/// Exits with an error, for mistakes in the arguments rather than broken state which still panics.
+fn fail(message: &str) -> ! {
+    eprintln!("{} {}", "error:".red(), message);
+    std::process::exit(1);
+}
+
+/// Resolves a registered context to its canonical path, or `None` if the file no longer exists.
+fn context_path(path: &str) -> Option<PathBuf> {
+    match Path::new(path).canonicalize() {
+        Ok(path) if path.is_file() => Some(path),
+        _ => None,
+    }
+}
+
+fn warn_missing_context(path: &str) {
+    warn(&format!("Skipping missing context {}, run `rmnd context prune` or `rmnd context mv` to fix it.", path));
+}
+
 fn get_all() -> ConfigSum {
     // fs::try_exists(CONFIG_DIR);
     // fs::try_exists(CONFIG_PATH);
     // fs::create_dir(CONFIG_DIR);
     // fs::write(CONFIG_PATH, contents);
     let global_config = load_global_config();
+    let global_path = global_config.path.clone().unwrap();
 
-    let mut result = ConfigSum::new();
-    result.priorities = global_config.priorities;
+    let mut result = ConfigSum::new(global_path.clone());
+    for priority in global_config.priorities {
+        result.priorities.push(LocalPriority { priority, path: global_path.clone() })
+    }
     // does crossbeam/tokio speed up multi io at all?
     for path in global_config.config_paths { 
-        let read = read_config(&Path::new(&path).to_path_buf());
-        for reminder in read.reminders {
-            result.reminders.push(LocalReminder { reminder, path: Path::new(&path).canonicalize().unwrap() })
+        let Some(path) = context_path(&path) else {
+            warn_missing_context(&path);
+            continue;
+        };
+        let read = read_config(&path);
+        result.contexts.push(path.clone());
+        for priority in read.priorities {
+            result.priorities.push(LocalPriority { priority, path: path.clone() })
         }
+        result.reminders.extend(local_reminders(read.reminders, &path, &path, &read.lines));
     }
 
-    for reminder in global_config.reminders {
-        result.reminders.push(LocalReminder { reminder, path: global_config.path.as_ref().unwrap().clone() })
+    for context in global_config.contexts {
+        let path = Path::new(&context.directory).join(CONFIG_NAME);
+        for priority in context.priorities {
+            result.priorities.push(LocalPriority { priority, path: path.clone() })
+        }
+        result.reminders.extend(local_reminders(context.reminders, &path, &global_path, &context.lines));
+        result.contexts.push(path.clone());
+        result.pathless.push(path);
     }
 
-    return result;
+    result.reminders.extend(local_reminders(global_config.reminders, &global_path, &global_path, &global_config.lines));
+
+    result
+}
+
+/// Wraps the reminders of the context at `path`, which are stored in `file` with each one defined on the matching entry
+/// of `lines`.
+fn local_reminders<'a>(reminders: Vec<Reminder>, path: &'a Path, file: &'a Path, lines: &'a [usize]) -> impl Iterator<Item = LocalReminder> + 'a {
+    reminders.into_iter().enumerate().map(move |(index, reminder)| {
+        let mut local = LocalReminder::new(reminder, path.to_path_buf());
+        local.defined_at = lines.get(index).map(|line| (file.to_path_buf(), *line));
+        local
+    })
 }
 
 fn get_local() -> ConfigSum {
+    let current_dir = current_dir().unwrap().canonicalize().unwrap(); // TODO no unwrap here
+    get_local_for(&current_dir)
+}
+
+/// Collects the contexts containing `current_dir`, for the current directory or the file given to `show --for-file`.
+fn get_local_for(current_dir: &Path) -> ConfigSum {
     // fs::try_exists(CONFIG_DIR);
     // fs::try_exists(CONFIG_PATH);
     // fs::create_dir(CONFIG_DIR);
     // fs::write(CONFIG_PATH, contents);
     let global_config = load_global_config();
 
-    let current_dir = current_dir().unwrap().canonicalize().unwrap(); // TODO no unwrap here
-
     // let local = current_dir.join(CONFIG_NAME);
     // if local.is_file() {
     //     let local_config = read_config(&local);
@@ -250,12 +873,19 @@ fn get_local() -> ConfigSum {
     //     return ConfigSum { priorities: global_config.priorities, reminders: local_config.reminders }
     // }
 
-    let mut result = ConfigSum::new();
-    result.priorities = global_config.priorities;
+    let global_path = global_config.path.clone().unwrap();
+
+    let mut result = ConfigSum::new(global_path.clone());
+    for priority in global_config.priorities {
+        result.priorities.push(LocalPriority { priority, path: global_path.clone() })
+    }
     // does crossbeam/tokio speed up multi io at all?
     // let mut closest = (u32::MAX, Path::new(""));
     for path in global_config.config_paths {
-        let path = PathBuf::from(&path).canonicalize().unwrap();
+        let Some(path) = context_path(&path) else {
+            warn_missing_context(&path);
+            continue;
+        };
         let container = path.parent().unwrap().to_path_buf();
 
         if current_dir.starts_with(&container) {
@@ -267,58 +897,158 @@ fn get_local() -> ConfigSum {
             // }
 
             let read = read_config(&path);
-            for reminder in read.reminders {
-                result.reminders.push(LocalReminder { reminder, path: path.clone() })
+            result.contexts.push(path.clone());
+            for priority in read.priorities {
+                result.priorities.push(LocalPriority { priority, path: path.clone() })
+            }
+            result.reminders.extend(local_reminders(read.reminders, &path, &path, &read.lines));
+        }
+    }
+
+    for context in global_config.contexts {
+        if current_dir.starts_with(&context.directory) {
+            let path = Path::new(&context.directory).join(CONFIG_NAME);
+            for priority in context.priorities {
+                result.priorities.push(LocalPriority { priority, path: path.clone() })
             }
+            result.reminders.extend(local_reminders(context.reminders, &path, &global_path, &context.lines));
+            result.contexts.push(path.clone());
+            result.pathless.push(path);
         }
     }
 
-    return result;
+    result
 }
 
-fn load_global_config() -> ConfigFile {
-    let CONFIG_PATH = PathBuf::from(CONFIG_DIR).canonicalize().unwrap().join(CONFIG_NAME);
+/// Writes `contents` to a temporary file next to `path` and renames it over `path`, so an interrupted save never leaves
+/// a half written config behind.
+fn write_atomically(path: &Path, contents: &str) -> std::io::Result<()> {
+    let name = path.file_name().and_then(|v| v.to_str()).unwrap_or("rmnd");
+    let temporary = path.with_file_name(format!(".{}.{}.tmp", name, std::process::id()));
+    fs::write(&temporary, contents).and_then(|_| fs::rename(&temporary, path)).inspect_err(|_| {
+        fs::remove_file(&temporary).ok();
+    })
+}
 
-    let mut global_config;
+fn save_config(config: &ConfigFile) {
+    // New reminders get their id here. Unchanged reminders of a rmnd.toml written without ids keep deriving theirs when
+    // read, see `layout::update`.
+    let mut config = config.clone();
+    config.assign_ids();
+    let config = &config;
 
-    if !Path::new(&CONFIG_DIR).exists() {
-        println!("creating dir: {}", CONFIG_DIR);
-        fs::create_dir(CONFIG_DIR).unwrap();
+    let path = config.path.as_ref().expect("Config file has no path to save to.");
+    if config.pathless {
+        let directory = path.parent().unwrap().to_str().unwrap();
+        let mut global_config = load_global_config();
+        let Some(context) = global_config.contexts.iter_mut().find(|v| v.directory == directory) else {
+            panic!("No pathless context exists for {}.", directory);
+        };
+        context.priorities = config.priorities.clone();
+        context.reminders = config.reminders.clone();
+        context.checklists = config.checklists.clone();
+        save_config(&global_config);
+        return;
     }
 
-    if !Path::new(&CONFIG_PATH).exists() {
-        global_config = ConfigFile::default_global_config();
-        let output = toml::to_string_pretty(&global_config).unwrap();
-        fs::write(&CONFIG_PATH, &output);
-    } else if !Path::new(&CONFIG_PATH).is_file() {
-        panic!("Something exists at {}, this is the default location for the global config.", CONFIG_PATH.to_str().unwrap());
-    } else {
-        global_config = read_config(&CONFIG_PATH);
+    let output = Format::of(path).write_over(config, fs::read_to_string(path).ok().as_deref());
+    if let Err(error) = write_atomically(path, &output) {
+        panic!("Failed to write {:?}: {:?}", path, error);
     }
 
-    global_config.path = Some(CONFIG_PATH);
-    global_config
+    if *path != global_config_path() {
+        write_known_copy(config, false);
+    }
 }
 
-fn find_most_local_config() -> PathBuf {
-    let global_config = load_global_config();
+fn global_config_path() -> PathBuf {
+    PathBuf::from(CONFIG_DIR).canonicalize().unwrap().join(CONFIG_NAME)
+}
 
-    let current_dir = current_dir().unwrap().canonicalize().unwrap(); // TODO no unwrap here
-    
-    println!("0");
-    let local = current_dir.join(CONFIG_NAME);
-    println!("1");
-    if local.is_file() {
-        println!("2");
-        return local;
+/// Known copies are keyed by the context's `settings.name` when it has one, so they survive the repository being
+/// cloned somewhere else, and by its path otherwise.
+fn known_copy_path(config: &ConfigFile, base: bool) -> PathBuf {
+    let key = match &config.settings.name {
+        Some(name) => format!("name-{}", name),
+        None => format!("path-{}", config.path.as_ref().unwrap().parent().unwrap().to_str().unwrap()),
+    };
+    let key: String = key.chars().map(|c| if c.is_alphanumeric() || c == '-' { c } else { '_' }).collect();
+    let suffix = if base { ".base.toml" } else { ".toml" };
+
+    PathBuf::from(CONFIG_DIR).join(KNOWN_DIR).join(key + suffix)
+}
+
+/// Keeps a copy of a local context. The plain copy tracks every save, the base copy is only updated when the file is
+/// adopted and is the common ancestor for the next three-way merge.
+fn write_known_copy(config: &ConfigFile, base: bool) {
+    let path = known_copy_path(config, base);
+    let output = toml::to_string_pretty(config).unwrap();
+    if fs::create_dir_all(path.parent().unwrap()).and_then(|_| fs::write(&path, output)).is_err() {
+        warn(&format!("Failed to keep a copy of this context at {:?}.", path));
     }
+}
 
-    let mut result = ConfigSum::new();
-    result.priorities = global_config.priorities;
-    // does crossbeam/tokio speed up multi io at all?
-    let mut closest = (usize::MAX, PathBuf::new());
-    for path in global_config.config_paths {
-        let path = PathBuf::from(&path).canonicalize().unwrap();
+fn read_known_copy(config: &ConfigFile, base: bool) -> Option<ConfigFile> {
+    let bytes = fs::read(known_copy_path(config, base)).ok()?;
+    toml::from_slice::<ConfigFile>(&bytes).ok()
+}
+
+fn load_global_config() -> ConfigFile {
+    if !Path::new(&CONFIG_DIR).exists() {
+        eprintln!("creating dir: {}", CONFIG_DIR);
+        fs::create_dir(CONFIG_DIR).unwrap();
+    }
+
+    let config_path = global_config_path();
+
+    let mut global_config;
+
+    if !Path::new(&config_path).exists() {
+        global_config = ConfigFile::default_global_config();
+        global_config.path = Some(config_path.clone());
+        save_config(&global_config);
+    } else if !Path::new(&config_path).is_file() {
+        panic!("Something exists at {}, this is the default location for the global config.", config_path.to_str().unwrap());
+    } else {
+        global_config = read_config(&config_path);
+    }
+
+    global_config.path = Some(config_path);
+    global_config
+}
+
+/// Loads the config for a context path, pathless contexts are read out of the global config.
+fn load_context(path: &Path) -> ConfigFile {
+    let global_config = load_global_config();
+    let directory = path.parent().unwrap().to_str().unwrap();
+    if let Some(context) = global_config.contexts.into_iter().find(|v| v.directory == directory) {
+        let mut config = ConfigFile::default_local_config();
+        config.priorities = context.priorities;
+        config.reminders = context.reminders;
+        config.checklists = context.checklists;
+        config.path = Some(path.to_path_buf());
+        config.pathless = true;
+        return config;
+    }
+
+    read_config(&path.to_path_buf())
+}
+
+fn find_most_local_config() -> PathBuf {
+    let global_config = load_global_config();
+
+    let current_dir = current_dir().unwrap().canonicalize().unwrap(); // TODO no unwrap here
+    
+    if let Some(local) = config_in(&current_dir) {
+        return local;
+    }
+
+    // does crossbeam/tokio speed up multi io at all?
+    let mut closest = (usize::MAX, PathBuf::new());
+    for path in global_config.config_paths {
+        let Some(path) = context_path(&path) else {
+            continue;
+        };
         let container = path.parent().unwrap().to_path_buf();
         if current_dir.starts_with(&container) {
             let remainder = current_dir.strip_prefix(&container).unwrap();
@@ -330,6 +1060,16 @@ fn find_most_local_config() -> PathBuf {
         }
     }
 
+    for context in global_config.contexts {
+        if let Ok(remainder) = current_dir.strip_prefix(&context.directory) {
+            let count = remainder.components().count();
+            if count < closest.0 {
+                closest.0 = count;
+                closest.1 = Path::new(&context.directory).join(CONFIG_NAME);
+            }
+        }
+    }
+
     if closest.0 == usize::MAX {
         return global_config.path.unwrap()
     }
@@ -339,38 +1079,136 @@ fn find_most_local_config() -> PathBuf {
 
 fn load_local_config() -> ConfigFile {
     let local_config = find_most_local_config();
-    let mut local = read_config(&local_config);
+    let mut local = load_context(&local_config);
     local.path = Some(local_config);
     local
 }
 
-fn show(sub_matches: &ArgMatches) {
-    println!("Showing...");
-
-    let reminders;
+/// The reminders chosen with `selection_args`, grouped by context with the global ones last, and the scope to evaluate
+/// them in. Reminders that are not active in the scope are still included.
+fn select(sub_matches: &ArgMatches) -> (ConfigSum, Scope) {
+    let for_file = sub_matches.get_one::<String>("for-file").map(|v| {
+        let path = current_dir().unwrap().join(v);
+        path.canonicalize().unwrap_or(path)
+    });
 
-    if sub_matches.get_one::<bool>("all") != None && *sub_matches.get_one::<bool>("all").unwrap()  {
-        reminders = get_all();
+    let mut reminders = if *sub_matches.get_one::<bool>("all").unwrap_or(&false) {
+        get_all()
+    } else if let Some(file) = &for_file {
+        get_local_for(file.parent().unwrap())
     } else {
-        reminders = get_local();
+        get_local()
+    };
+
+    if *sub_matches.get_one::<bool>("scan").unwrap_or(&false) {
+        for scanned in scan_contexts(&reminders) {
+            // Keep reminders grouped by context, the global ones come last.
+            let index = match reminders.reminders.iter().rposition(|v| v.path == scanned.path) {
+                Some(index) => index + 1,
+                None => reminders
+                    .reminders
+                    .iter()
+                    .position(|v| v.path == reminders.global_path)
+                    .unwrap_or(reminders.reminders.len()),
+            };
+            reminders.reminders.insert(index, scanned);
+        }
     }
-    
-    // let sorted_reminders = reminders.reminders.group_by(|a, b| { a.reminder.priority == b.reminder.priority });
-    let sorted_reminders = reminders.reminders.group_by(|a, b| { a.path == b.path });
-    
-    for reminder_group in sorted_reminders {
-        println!("{}", reminder_group[0].path.to_str().unwrap());
+
+    if let Some(priorities) = sub_matches.get_one::<String>("priorities") {
+        let wanted: Vec<&str> = priorities.split(',').map(|v| v.trim()).filter(|v| !v.is_empty()).collect();
+        let matching: Vec<bool> = reminders
+            .reminders
+            .iter()
+            .map(|reminder| {
+                let name = &reminder.reminder.priority;
+                let id = reminders.resolve_priority(name, &reminder.path).map(|v| v.id.as_str());
+                wanted.iter().any(|v| v.eq_ignore_ascii_case(name) || Some(*v) == id)
+            })
+            .collect();
+        let mut matching = matching.into_iter();
+        reminders.reminders.retain(|_| matching.next().unwrap());
+    }
+
+    let scope = match for_file {
+        Some(file) => Scope::new(&reminders.global_path, file),
+        None => Scope::current(&reminders.global_path),
+    };
+    (reminders, scope)
+}
+
+fn show(sub_matches: &ArgMatches) {
+    let quickfix = sub_matches.get_one::<String>("format").map(|v| v == "quickfix").unwrap_or(false);
+    if !quickfix {
+        println!("Showing...");
+    }
+
+    let (reminders, mut scope) = select(sub_matches);
+
+    let explain = *sub_matches.get_one::<bool>("explain").unwrap_or(&false);
+    let show_ids = *sub_matches.get_one::<bool>("show-ids").unwrap_or(&false);
+
+    let visible: Vec<&LocalReminder> = reminders
+        .reminders
+        .iter()
+        .filter(|v| explain || scope.is_active(v))
+        .collect();
+    let mut visible = visible.into_iter().peekable();
+
+    while let Some(first) = visible.next() {
+        let mut reminder_group = vec![first];
+        while let Some(next) = visible.next_if(|v| v.path == first.path) {
+            reminder_group.push(next);
+        }
+
+        if quickfix {
+            for reminder in reminder_group {
+                let (file, line) = quickfix_location(reminder);
+                println!("{}:{}:1: {}: {}", file.to_str().unwrap(), line, reminder.reminder.priority, reminder.reminder.text);
+            }
+            continue;
+        }
+
+        println!("{}", reminders.display_path(&reminder_group[0].path));
         for reminder in reminder_group {
             // println!("{}", reminder.reminder.text);
             
-            if let Some(priority) = reminders.priorities.iter().find(|v| { v.name == reminder.reminder.priority }) {
-                println!("{}", reminder.reminder.text.color(priority.color));
+            let source = match &reminder.source {
+                Some((file, line)) => {
+                    let file = file.strip_prefix(reminder.path.parent().unwrap()).unwrap_or(file);
+                    format!(" ({}:{})", file.to_str().unwrap(), line).dimmed()
+                },
+                None => "".normal(),
+            };
+
+            let id = if show_ids && !reminder.reminder.id.is_empty() { format!("{} ", reminder.reminder.id).dimmed() } else { "".normal() };
+
+            if let Some(priority) = reminders.resolve_priority(&reminder.reminder.priority, &reminder.path) {
+                println!("{}{}{}", id, reminder.reminder.text.color(priority.color), source);
             } else {
-                // white & log couldn't find?
+                println!("{}{}{} {}", id, reminder.reminder.text.dimmed(), source, format!("(unknown priority: {})", reminder.reminder.priority).yellow());
+            }
+
+            if explain {
+                let checks = scope.checks(reminder, false);
+                if checks.iter().all(|v| v.holds) {
+                    println!("    {}", "shown".green());
+                } else {
+                    println!("    {}", "hidden".red());
+                }
+                for check in checks {
+                    let result = if check.holds { "holds".green() } else { "fails".red() };
+                    println!("    {} {} ({})", result, check.condition, check.detail);
+                }
             }
         }
     }
 
+    let orphaned = reminders.orphans().count();
+    if orphaned > 0 {
+        warn(&format!("{} reminder(s) reference an undefined priority, run `rmnd check` to fix them.", orphaned));
+    }
+
     /*
     println!("Showing {}", sub_matches.get_one::<String>("REMOTE").expect("required"));
              let add_command = sub_matches.subcommand().unwrap_or(("push", sub_matches));
@@ -394,62 +1232,306 @@ fn show(sub_matches: &ArgMatches) {
     */
 }
 
-fn find_priority(name: String) -> Priority {
-    let global = load_global_config();
-    if let Some(priority) = global.priorities.iter().find(|v| { v.name == name }) {
+/// Where `show --format quickfix` points a reminder to: the comment a scanned reminder was found in, the file its
+/// anchor names, or else the line it is defined on.
+fn quickfix_location(reminder: &LocalReminder) -> (PathBuf, usize) {
+    if let Some(source) = &reminder.source {
+        return source.clone();
+    }
+
+    if let Some(anchor) = &reminder.reminder.anchor {
+        let file = reminder.path.parent().unwrap().join(anchor);
+        if file.is_file() {
+            return (file, 1);
+        }
+    }
+
+    reminder.defined_at.clone().unwrap_or_else(|| (reminder.path.clone(), 1))
+}
+
+/// The result of one of a reminder's conditions, as listed by `show --explain`.
+struct Check {
+    condition: String,
+    holds: bool,
+    detail: String,
+}
+
+/// What reminders are evaluated against: the path being worked on, the current directory or the file given to
+/// `show --for-file`, and the branches checked out, cached per directory since `show --all` touches many contexts.
+struct Scope {
+    global_path: PathBuf,
+    target: PathBuf,
+    branches: HashMap<PathBuf, Option<String>>,
+}
+
+impl Scope {
+    fn new(global_path: &Path, target: PathBuf) -> Self {
+        Self { global_path: global_path.to_path_buf(), target, branches: HashMap::new() }
+    }
+
+    fn current(global_path: &Path) -> Self {
+        Self::new(global_path, current_dir().unwrap().canonicalize().unwrap())
+    }
+
+    /// Evaluates a reminder's anchor, branch condition and `when` conditions against its context's directory, or the
+    /// target's directory for global reminders. With `short_circuit` evaluation stops at the first failing condition.
+    fn checks(&mut self, reminder: &LocalReminder, short_circuit: bool) -> Vec<Check> {
+        let target_dir = if self.target.is_dir() { self.target.clone() } else { self.target.parent().unwrap().to_path_buf() };
+        let dir = if reminder.path == self.global_path {
+            target_dir
+        } else {
+            reminder.path.parent().unwrap().to_path_buf()
+        };
+
+        let mut checks = vec![];
+
+        if let Some(done_at) = reminder.reminder.done_at {
+            checks.push(Check { condition: "done".to_string(), holds: false, detail: format!("at {}", done_at.format("%Y-%m-%d %H:%M")) });
+        }
+
+        if let Some(until) = reminder.reminder.snoozed_until {
+            let holds = until <= Local::now();
+            let detail = if holds { "the snooze has ended" } else { "still snoozed" };
+            checks.push(Check { condition: format!("snoozed until {}", until.format("%Y-%m-%d %H:%M")), holds, detail: detail.to_string() });
+        }
+
+        if let Some(anchor) = &reminder.reminder.anchor {
+            let holds = anchor_matches(&dir, anchor, &self.target);
+            let detail = match self.target.strip_prefix(&dir) {
+                Ok(relative) if relative.as_os_str().is_empty() => "at the context root".to_string(),
+                Ok(relative) => format!("at {}", relative.to_str().unwrap()),
+                Err(_) => format!("at {}", self.target.to_str().unwrap()),
+            };
+            checks.push(Check { condition: format!("anchor {}", anchor), holds, detail });
+        }
+
+        if let Some(pattern) = &reminder.reminder.branch {
+            if !(short_circuit && checks.iter().any(|v| !v.holds)) {
+                let branch = self.branches.entry(dir.clone()).or_insert_with_key(|dir| git::current_branch(dir));
+                let (holds, detail) = match branch {
+                    Some(branch) => (git::branch_matches(pattern, branch), format!("on {}", branch)),
+                    None => (false, "no branch is checked out".to_string()),
+                };
+                checks.push(Check { condition: format!("branch {}", pattern), holds, detail });
+            }
+        }
+
+        for when in &reminder.reminder.when {
+            if short_circuit && checks.iter().any(|v| !v.holds) {
+                break;
+            }
+
+            let (holds, detail) = match when.parse::<conditions::Condition>() {
+                Ok(condition) => {
+                    let outcome = condition.evaluate(&dir);
+                    (outcome.holds, outcome.detail)
+                },
+                Err(error) => (false, error),
+            };
+            checks.push(Check { condition: when.clone(), holds, detail });
+        }
+
+        checks
+    }
+
+    /// Whether every condition of a reminder holds, so that it should be shown.
+    fn is_active(&mut self, reminder: &LocalReminder) -> bool {
+        self.checks(reminder, true).iter().all(|v| v.holds)
+    }
+}
+
+/// Whether `target` is within a reminder's anchor, a glob relative to the context's directory `dir` unless it is
+/// absolute. The target matches when it, or any directory containing it, matches the glob.
+fn anchor_matches(dir: &Path, anchor: &str, target: &Path) -> bool {
+    let anchor = anchor.trim_end_matches('/');
+    let full = if Path::new(anchor).is_absolute() { anchor.to_string() } else { format!("{}/{}", glob::Pattern::escape(dir.to_str().unwrap()), anchor) };
+    // `dir/**` should also match `dir` itself.
+    let patterns: Vec<glob::Pattern> = [full.as_str(), full.trim_end_matches("/**")]
+        .iter()
+        .filter_map(|v| glob::Pattern::new(v).ok())
+        .collect();
+    let options = glob::MatchOptions { require_literal_separator: true, ..Default::default() };
+
+    target.ancestors().any(|candidate| patterns.iter().any(|v| v.matches_path_with(candidate, options)))
+}
+
+/// Finds a priority by name as seen from `path`, ignoring case when that is unambiguous. Otherwise, or when `name` is
+/// empty, one is picked among those defined for `path`.
+fn find_priority(name: String, path: &Path) -> Priority {
+    let all = get_all();
+    if let Some(priority) = all.resolve_priority(&name, path) {
         return priority.clone();
     }
 
-    panic!("Could not find an existing priority based on the name: {}", name); // TODO could fuzzy find here
+    let mut names: Vec<String> = vec![];
+    for priority in &all.priorities {
+        let name = &priority.priority.name;
+        if !names.contains(name) && all.resolve_priority(name, path).is_some() {
+            names.push(name.clone());
+        }
+    }
+
+    let insensitive: Vec<&String> = names.iter().filter(|v| v.eq_ignore_ascii_case(&name)).collect();
+    if let [found] = insensitive.as_slice() {
+        return all.resolve_priority(found, path).unwrap().clone();
+    }
+
+    let query = if name.is_empty() { None } else { Some(name.as_str()) };
+    let index = choose("priority", query, &names);
+    all.resolve_priority(&names[index], path).unwrap().clone()
+}
+
+/// Lets the user pick one of `labels` starting from `query`, or exits listing the closest ones when there is no
+/// terminal to show a picker on.
+fn choose(what: &str, query: Option<&str>, labels: &[String]) -> usize {
+    if labels.is_empty() {
+        fail(&format!("There is no {} to pick from.", what));
+    }
+
+    if picker::interactive() {
+        match picker::pick(&format!("Pick a {}", what), labels, query.unwrap_or("")) {
+            Some(index) => index,
+            None => fail("Nothing was picked."),
+        }
+    } else {
+        match query {
+            Some(query) => fail(&picker::did_you_mean(what, query, labels)),
+            None => fail(&format!("No {} was given.", what)),
+        }
+    }
+}
+
+/// The reminders commands like `remove` can refer to, those of the current context chain followed by the global ones.
+fn get_addressable() -> ConfigSum {
+    let mut sum = get_local();
+    let global_config = load_global_config();
+    sum.reminders.extend(local_reminders(global_config.reminders, &sum.global_path, &sum.global_path, &global_config.lines));
+    sum
+}
+
+/// Finds the reminder an argument refers to by its id or exact text, otherwise one is picked among all of them.
+fn find_reminder<'a>(query: Option<&str>, sum: &'a ConfigSum) -> &'a LocalReminder {
+    if let Some(query) = query {
+        let exact: Vec<&LocalReminder> = sum.reminders.iter().filter(|v| v.reminder.id == query || v.reminder.text == query).collect();
+        if let [found] = exact.as_slice() {
+            return found;
+        }
+    }
+
+    let labels: Vec<String> = sum
+        .reminders
+        .iter()
+        .map(|v| {
+            let global = if v.path == sum.global_path { " (global)" } else { "" };
+            format!("{} {}: {}{}", v.reminder.id, v.reminder.priority, v.reminder.text, global)
+        })
+        .collect();
+    &sum.reminders[choose("reminder", query, &labels)]
+}
+
+/// Changes a reminder in its config file, found by id since everything else may be what changes.
+fn update_reminder(local: &LocalReminder, change: impl FnOnce(&mut Vec<Reminder>, usize)) {
+    let mut config = load_context(&local.path);
+    let Some(index) = config.reminders.iter().position(|v| v.id == local.reminder.id) else {
+        fail(&format!("The reminder {} is no longer in {}.", local.reminder.id, local.path.to_str().unwrap()));
+    };
+    change(&mut config.reminders, index);
+    save_config(&config);
 }
 
 fn add(sub_matches: &ArgMatches) {
     let subcommand = sub_matches.subcommand();
     let global: bool = *sub_matches.get_one("global").unwrap_or(&false);
 
-    if subcommand != None && subcommand.unwrap().0 == "priority" {
-        let text: String = subcommand.unwrap().1.get_one::<String>("priority").unwrap().clone();
-        let mut config = load_global_config();
-        let color: String = subcommand.unwrap().1.get_one::<String>("color").unwrap().clone(); // TODO color parsing
+    if let Some(("priority", sub_matches)) = subcommand {
+        let text: String = sub_matches.get_one::<String>("priority").unwrap().clone();
+        let mut config = if global { load_global_config() } else { load_local_config() };
+
+        let color = match sub_matches.get_one::<String>("color") {
+            Some(color) => match color.parse::<colored::Color>() {
+                Ok(color) => color.into(),
+                Err(_) => panic!("Unknown color: {}", color),
+            },
+            None => reminder::Color::White,
+        };
+
+        if config.priorities.iter().any(|v| v.name == text) {
+            panic!("A priority named {} already exists in {:?}.", text, config.path.unwrap());
+        }
 
-        // TODO color
-        
         // TODO the id
-        config.priorities.push(Priority { name: text, id: String::from("0"), color: reminder::Color::Cyan });
-        let output = toml::to_string_pretty(&config).unwrap();
-        fs::write(&config.path.unwrap(), &output);
+        let id = config.priorities.len().to_string();
+        config.priorities.push(Priority::new(text, id, color));
+        save_config(&config);
     } else {
-        if let Some(text) = sub_matches.get_one::<String>("reminder") {
-            // let author: String = sub_matches.get_one::<String>("author").unwrap().clone(); // TODO: figure this out
-            let author = "".to_string();
-            let priority: String = sub_matches.get_one::<String>("priority").unwrap().clone(); // TODO: figure this out
-
-            add_reminder(global, text.clone(), priority, author);
+        let sub_matches = if sub_matches.contains_id("reminder") {
+            sub_matches
         } else {
-            let sub_matches = sub_matches.subcommand_matches("reminder").unwrap();
-            let text = sub_matches.get_one::<String>("reminder").unwrap();
+            sub_matches.subcommand_matches("reminder").unwrap()
+        };
 
-            let author = "".to_string();
-            let priority: String = sub_matches.get_one::<String>("priority").unwrap().clone(); // TODO: figure this out
-
-            add_reminder(global, text.clone(), priority, author);
-        }
+        add_reminder(global, reminder_from_matches(sub_matches));
     }
 }
 
-fn add_reminder(global: bool, text: String, priority: String, author: String) {
-    let mut config;
-    if global {
-        config = load_global_config();
+/// Builds a reminder from the arguments shared by `add <reminder>` and `add reminder`.
+fn reminder_from_matches(sub_matches: &ArgMatches) -> Reminder {
+    let text = sub_matches.get_one::<String>("reminder").unwrap().clone();
+    // let author: String = sub_matches.get_one::<String>("author").unwrap().clone(); // TODO: figure this out
+    let author = "".to_string();
+    // An empty priority is picked by `find_priority`.
+    let priority = sub_matches.get_one::<String>("priority").cloned().unwrap_or_default();
+
+    let branch = if sub_matches.contains_id("branch") {
+        match sub_matches.get_one::<String>("branch") {
+            Some(branch) => Some(branch.clone()),
+            None => match git::current_branch(&current_dir().unwrap()) {
+                Some(branch) => Some(branch),
+                None => panic!("--branch was given without a value, but no branch is checked out here."),
+            },
+        }
     } else {
-        config = load_local_config();
+        None
+    };
+
+    let events = sub_matches.get_many::<String>("event").into_iter().flatten().cloned().collect();
+    let blocking = *sub_matches.get_one::<bool>("blocking").unwrap_or(&false);
+
+    let command = sub_matches.get_one::<String>("command").cloned();
+    let confirm = *sub_matches.get_one::<bool>("confirm").unwrap_or(&false);
+
+    let when = sub_matches.get_many::<String>("when").into_iter().flatten().cloned().collect();
+
+    let anchor = sub_matches.get_one::<String>("anchor").cloned();
+
+    let due = sub_matches.get_one::<NaiveDate>("due").copied();
+    let remind_at = sub_matches.get_one::<DateTime<Local>>("remind-at").copied();
+    let every = sub_matches.get_one::<String>("every").cloned();
+    if every.is_some() && remind_at.is_none() && due.is_none() {
+        fail("--every repeats from --remind-at or --due, give one of them.");
     }
 
-    let priority = find_priority(priority);
+    Reminder { priority, author, text, branch, events, blocking, command, confirm, when, anchor, due, remind_at, every, ..Default::default() }
+}
+
+fn add_reminder(global: bool, mut reminder: Reminder) {
+    let mut config = if global { load_global_config() } else { load_local_config() };
+
+    let priority = find_priority(reminder.priority, config.path.as_ref().unwrap());
+    reminder.priority = priority.name;
 
-    config.reminders.push(Reminder { priority: priority.name, author, text });
-    let output = toml::to_string_pretty(&config).unwrap();
-    fs::write(&config.path.unwrap(), &output);
+    // Anchors are given relative to the current directory but stored relative to the context.
+    if let Some(anchor) = &reminder.anchor {
+        let context_dir = config.path.as_ref().unwrap().parent().unwrap();
+        let absolute = current_dir().unwrap().canonicalize().unwrap().join(anchor);
+        reminder.anchor = Some(match absolute.strip_prefix(context_dir) {
+            Ok(relative) => relative.to_str().unwrap().to_string(),
+            Err(_) => absolute.to_str().unwrap().to_string(),
+        });
+    }
+
+    config.reminders.push(reminder);
+    save_config(&config);
 }
 
 fn remind(sub_matches: &ArgMatches) {
@@ -464,34 +1546,1040 @@ fn remind(sub_matches: &ArgMatches) {
                 // let priority: String = sub_matches.get_one::<String>("priority").unwrap().clone(); // TODO: figure this out
                 let priority = "Critical".to_string();
 
-                add_reminder(global, text, priority, author);
-            },
-            Some(("remove", sub_matches)) => {
-                // find the specific reminder
-                // use function so "remove()" can also use it
+                add_reminder(global, Reminder { priority, author, text, ..Default::default() });
             },
+            Some(("remove", sub_matches)) => remove(sub_matches),
             Some(("edit", sub_matches)) => {
-                // find the specific reminder
+                let sum = get_addressable();
+                let local = find_reminder(sub_matches.get_one::<String>("reminder").map(|v| v.as_str()), &sum);
+
+                let priority = sub_matches.get_one::<String>("priority").map(|v| find_priority(v.clone(), &local.path).name);
+                let mut text = sub_matches.get_one::<String>("text").cloned();
+                if text.is_none() && priority.is_none() {
+                    println!("{}", local.reminder.text);
+                    print!("New text (empty to keep): ");
+                    std::io::Write::flush(&mut std::io::stdout()).ok();
+                    let line: String = try_read!("{}\n").unwrap_or_default();
+                    text = Some(line.trim().to_string()).filter(|v| !v.is_empty());
+                }
+
+                update_reminder(local, |reminders, index| {
+                    if let Some(text) = text {
+                        reminders[index].text = text;
+                    }
+                    if let Some(priority) = priority {
+                        reminders[index].priority = priority;
+                    }
+                });
+                println!("Updated {}.", local.reminder.id);
             },
             Some((_, _)) => {
                 panic!("Unknown subcommand.");
             }
         }
+}
+
+fn remove(sub_matches: &ArgMatches) {
+    let sum = get_addressable();
+    let local = find_reminder(sub_matches.get_one::<String>("reminder").map(|v| v.as_str()), &sum);
+    update_reminder(local, |reminders, index| {
+        reminders.remove(index);
+    });
+    println!("Removed \"{}\" from {}.", local.reminder.text, sum.display_path(&local.path));
+}
+
+fn prio(_sub_matches: &ArgMatches) {
+    println!("Priorities...");
+}
+
+/// Turns a directory or file argument into the absolute path of a context file, without requiring it to exist.
+fn context_file_arg(arg: &str) -> PathBuf {
+    let mut path = current_dir().unwrap().join(arg);
+    if path.is_dir() || !path.file_name().map(is_config_name).unwrap_or(false) {
+        path = config_in(&path).unwrap_or_else(|| path.join(CONFIG_NAME));
+    }
+    path.canonicalize().unwrap_or(path)
+}
+
+/// Finds the index of a registered context, comparing both the stored and the canonical path.
+fn find_registered(global_config: &ConfigFile, path: &Path) -> Option<usize> {
+    global_config.config_paths.iter().position(|v| {
+        Path::new(v) == path || context_path(v).as_deref() == Some(path)
+    })
+}
+
+/// Finds the index of the pathless context addressed by the virtual path `directory/rmnd.toml`.
+fn find_pathless(global_config: &ConfigFile, path: &Path) -> Option<usize> {
+    let directory = path.parent()?;
+    global_config.contexts.iter().position(|v| Path::new(&v.directory) == directory)
+}
+
+fn confirm(question: &str) -> bool {
+    loop {
+        println!("{} [y/n]", question);
+        // Treat a closed stdin as a no rather than asking forever.
+        let Ok(input) = try_read!() else {
+            return false;
+        };
+        let input: char = input;
+        if input.to_lowercase().to_string() == "y" {
+            return true;
+        } else if input.to_lowercase().to_string() == "n" {
+            return false;
+        }
+    }
+}
+
+fn context(sub_matches: &ArgMatches) {
+    let mut global_config = load_global_config();
+
+    match sub_matches.subcommand() {
+        Some(("list", _)) => {
+            let global_path = global_config.path.as_ref().unwrap();
+            println!("{} ({} reminders, global)", global_path.to_str().unwrap(), global_config.reminders.len());
+            for path in &global_config.config_paths {
+                match context_path(path) {
+                    Some(canonical) => {
+                        let config = read_config(&canonical);
+                        let tracking = match git::tracking(&canonical) {
+                            git::Tracking::Tracked => ", tracked",
+                            git::Tracking::Ignored => ", private",
+                            git::Tracking::Untracked => ", untracked",
+                            git::Tracking::NotInRepo => "",
+                        };
+                        println!("{} ({} reminders{})", path, config.reminders.len(), tracking);
+                    },
+                    None => println!("{} {}", path, "(missing)".red()),
+                }
+            }
+            for context in &global_config.contexts {
+                let missing = if Path::new(&context.directory).is_dir() { "" } else { " (missing)" };
+                println!("{} ({} reminders, no file){}", context.directory, context.reminders.len(), missing.red());
+            }
+        },
+        Some(("add", sub_matches)) => {
+            let path = context_file_arg(sub_matches.get_one::<String>("path").map(|v| v.as_str()).unwrap_or("."));
+            if !path.is_file() {
+                panic!("No context file exists at {:?}, use `rmnd init` to create one.", path);
+            }
+            if find_registered(&global_config, &path).is_some() {
+                println!("{:?} is already registered.", path);
+                return;
+            }
+
+            // Make sure the file parses before registering it.
+            read_config(&path);
+            global_config.config_paths.push(path.to_str().unwrap().to_string());
+            save_config(&global_config);
+            println!("Registered {:?}.", path);
+        },
+        Some(("remove", sub_matches)) => {
+            let path = context_file_arg(sub_matches.get_one::<String>("path").unwrap());
+            if let Some(index) = find_pathless(&global_config, &path) {
+                let count = global_config.contexts[index].reminders.len();
+                if count > 0 && !confirm(&format!("This pathless context holds {} reminder(s) that will be deleted, continue?", count)) {
+                    println!("Nothing to do.");
+                    return;
+                }
+
+                let removed = global_config.contexts.remove(index);
+                save_config(&global_config);
+                println!("Removed {}.", removed.directory);
+                return;
+            }
+
+            match find_registered(&global_config, &path) {
+                Some(index) => {
+                    let removed = global_config.config_paths.remove(index);
+                    save_config(&global_config);
+                    println!("Unregistered {}.", removed);
+                },
+                None => panic!("{:?} is not a registered context.", path),
+            }
+        },
+        Some(("mv", sub_matches)) => {
+            let from = context_file_arg(sub_matches.get_one::<String>("from").unwrap());
+            let to = context_file_arg(sub_matches.get_one::<String>("to").unwrap());
+            if let Some(index) = find_pathless(&global_config, &from) {
+                let directory = to.parent().unwrap();
+                if !directory.is_dir() {
+                    panic!("{:?} is not a directory.", directory);
+                }
+                if find_pathless(&global_config, &to).is_some() {
+                    panic!("{:?} already has a pathless context.", directory);
+                }
+
+                global_config.contexts[index].directory = directory.to_str().unwrap().to_string();
+                save_config(&global_config);
+                println!("Moved {:?} to {:?}.", from.parent().unwrap(), directory);
+                return;
+            }
+
+            let Some(index) = find_registered(&global_config, &from) else {
+                panic!("{:?} is not a registered context.", from);
+            };
+            if !to.is_file() {
+                panic!("No context file exists at {:?}.", to);
+            }
+            if find_registered(&global_config, &to).is_some() {
+                panic!("{:?} is already registered.", to);
+            }
+
+            global_config.config_paths[index] = to.to_str().unwrap().to_string();
+            save_config(&global_config);
+            println!("Moved {:?} to {:?}.", from, to);
+        },
+        Some(("prune", _)) => {
+            let (kept, pruned): (Vec<String>, Vec<String>) =
+                global_config.config_paths.drain(..).partition(|v| context_path(v).is_some());
+            global_config.config_paths = kept;
+
+            if pruned.is_empty() {
+                println!("Nothing to prune.");
+                return;
+            }
+
+            save_config(&global_config);
+            for path in pruned {
+                println!("Pruned {}.", path);
+            }
+        },
+        _ => unreachable!(),
+    }
+}
+
+fn git_command(sub_matches: &ArgMatches) {
+    let current_dir = current_dir().unwrap().canonicalize().unwrap();
+    let Some(repo) = git::Repo::find(&current_dir) else {
+        panic!("{:?} is not inside a git repository.", current_dir);
+    };
+
+    match sub_matches.subcommand() {
+        Some(("install-hooks", sub_matches)) => {
+            let force = *sub_matches.get_one::<bool>("force").unwrap_or(&false);
+            for event in git::HOOKS {
+                match repo.install_hook(event, force) {
+                    Ok(true) => println!("Installed {:?}.", repo.hooks_dir().join(event)),
+                    Ok(false) => warn(&format!("A {} hook not installed by rmnd already exists, use --force to replace it.", event)),
+                    Err(error) => panic!("Failed to install the {} hook: {:?}", event, error),
+                }
+            }
+        },
+        Some(("install-merge-driver", _)) => {
+            // Patterns without a slash match in every directory.
+            match repo.add_merge_attributes(&CONFIG_NAMES) {
+                Ok(added) if added.is_empty() => println!(".gitattributes already assigns the merge driver."),
+                Ok(added) => println!("Added {} to .gitattributes, commit it to share it.", added.join(", ")),
+                Err(error) => panic!("Failed to write .gitattributes: {:?}", error),
+            }
+            if !repo.configure_merge_driver() {
+                fail("Failed to register the merge driver with `git config`.");
+            }
+            println!("Registered the merge driver in this clone, run this again in every other clone.");
+        },
+        Some(("hook", sub_matches)) => {
+            let event = sub_matches.get_one::<String>("event").unwrap();
+            if event == "post-checkout" {
+                let matches = cli().get_matches_from(["rmnd", "show"]);
+                show(matches.subcommand_matches("show").unwrap());
+                return;
+            }
+
+            // Every context within the repository applies, as well as those containing it and the global one.
+            let all = get_all();
+            let mut scope = Scope::current(&all.global_path);
+            let reminders: Vec<&LocalReminder> = all
+                .reminders
+                .iter()
+                .filter(|v| v.reminder.events.contains(event))
+                .filter(|v| {
+                    let dir = v.path.parent().unwrap();
+                    v.path == all.global_path || dir.starts_with(&repo.work_tree) || repo.work_tree.starts_with(dir)
+                })
+                .filter(|v| scope.is_active(v))
+                .collect();
+
+            let mut blocking = 0;
+            for reminder in &reminders {
+                let text = match all.resolve_priority(&reminder.reminder.priority, &reminder.path) {
+                    Some(priority) => reminder.reminder.text.color(priority.color),
+                    None => reminder.reminder.text.normal(),
+                };
+                if reminder.reminder.blocking {
+                    blocking += 1;
+                    println!("{} {}", "[blocking]".red(), text);
+                } else {
+                    println!("{}", text);
+                }
+            }
+
+            if blocking > 0 {
+                let verb = if event == "pre-push" { "push" } else { "commit" };
+                eprintln!("{} reminder(s) block this {}, remove them or bypass the hook with `git {} --no-verify`.", blocking, verb, verb);
+                std::process::exit(1);
+            }
+        },
+        _ => unreachable!(),
+    }
+}
+
+const ZSH_HOOK: &str = r#"# Add to ~/.zshrc: eval "$(rmnd exec --shell-hook zsh)"
+_rmnd_preexec() { rmnd exec --check -- "$1" }
+autoload -Uz add-zsh-hook
+add-zsh-hook preexec _rmnd_preexec
+"#;
+
+const BASH_HOOK: &str = r#"# Add to ~/.bashrc: eval "$(rmnd exec --shell-hook bash)"
+_rmnd_preexec() { rmnd exec --check -- "$1"; }
+if [ -n "${bash_preexec_imported:-}" ]; then
+    preexec_functions+=(_rmnd_preexec)
+else
+    trap '[ -z "$COMP_LINE" ] && [ "$BASH_COMMAND" != "$PROMPT_COMMAND" ] && _rmnd_preexec "$BASH_COMMAND"' DEBUG
+fi
+"#;
+
+fn exec(sub_matches: &ArgMatches) {
+    if let Some(shell) = sub_matches.get_one::<String>("shell-hook") {
+        print!("{}", if shell == "zsh" { ZSH_HOOK } else { BASH_HOOK });
+        return;
+    }
+
+    let command: Vec<OsString> = sub_matches.get_many::<OsString>("command").into_iter().flatten().cloned().collect();
+    if command.is_empty() {
+        panic!("No command given, use `rmnd exec -- <command>`.");
+    }
+    let line = command.iter().map(|v| v.to_string_lossy()).collect::<Vec<_>>().join(" ");
+
+    // Global reminders apply too, a command is as likely to be dangerous anywhere.
+    let mut reminders = get_local();
+    let global_config = load_global_config();
+    for reminder in global_config.reminders {
+        reminders.reminders.push(LocalReminder::new(reminder, reminders.global_path.clone()));
+    }
+
+    let mut scope = Scope::current(&reminders.global_path);
+    let matching: Vec<&LocalReminder> = reminders
+        .reminders
+        .iter()
+        .filter(|v| match &v.reminder.command {
+            Some(pattern) => glob::Pattern::new(pattern).map(|p| p.matches(&line)).unwrap_or(*pattern == line),
+            None => false,
+        })
+        .filter(|v| scope.is_active(v))
+        .collect();
+
+    for reminder in &matching {
+        match reminders.resolve_priority(&reminder.reminder.priority, &reminder.path) {
+            Some(priority) => eprintln!("{}", reminder.reminder.text.color(priority.color)),
+            None => eprintln!("{}", reminder.reminder.text),
+        }
+    }
+
+    if *sub_matches.get_one::<bool>("check").unwrap_or(&false) {
+        return;
+    }
+
+    let yes = *sub_matches.get_one::<bool>("yes").unwrap_or(&false);
+    if !yes && matching.iter().any(|v| v.reminder.confirm) && !confirm(&format!("Run `{}`?", line)) {
+        eprintln!("Not running `{}`.", line);
+        std::process::exit(1);
+    }
+
+    let mut process = std::process::Command::new(&command[0]);
+    process.args(&command[1..]);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        let error = process.exec();
+        panic!("Failed to run `{}`: {:?}", line, error);
+    }
+
+    #[cfg(not(unix))]
+    match process.status() {
+        Ok(status) => std::process::exit(status.code().unwrap_or(1)),
+        Err(error) => panic!("Failed to run `{}`: {:?}", line, error),
+    }
+}
+
+/// Opens `file` in `$VISUAL` or `$EDITOR`, run through the shell so it may carry arguments such as `code --wait`.
+/// Returns whether the editor exited successfully.
+fn run_editor(file: &Path) -> bool {
+    let editor = std::env::var("VISUAL").or_else(|_| std::env::var("EDITOR")).unwrap_or_else(|_| "vi".to_string());
+    std::process::Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$1\"", editor))
+        .arg("sh")
+        .arg(file)
+        .status()
+        .map(|v| v.success())
+        .unwrap_or(false)
+}
+
+/// Loads the context chosen with `context_args`.
+fn chosen_config(sub_matches: &ArgMatches) -> ConfigFile {
+    if *sub_matches.get_one::<bool>("global").unwrap_or(&false) {
+        load_global_config()
+    } else if let Some(context) = sub_matches.get_one::<String>("context") {
+        let path = context_file_arg(context);
+        let global_config = load_global_config();
+        if !path.is_file() && find_pathless(&global_config, &path).is_none() {
+            fail(&format!("There is no context at {}.", path.to_str().unwrap()));
+        }
+        load_context(&path)
+    } else {
+        load_local_config()
+    }
+}
+
+/// The names of the priorities reminders of `config` may use, from most to least important, including ones the file
+/// defines itself but that are not registered yet. Also returns the sum they were resolved in.
+fn available_priorities(config: &ConfigFile) -> (ConfigSum, Vec<String>) {
+    let path = config.path.clone().unwrap();
+    let global_path = global_config_path();
+    let mut sum = if path == global_path { ConfigSum::new(global_path) } else { get_local_for(path.parent().unwrap()) };
+    sum.priorities.retain(|v| v.path != path);
+    for priority in &config.priorities {
+        sum.priorities.push(LocalPriority { priority: priority.clone(), path: path.clone() });
+    }
+
+    let names = ranked_priorities(&sum, &path);
+    (sum, names)
+}
+
+/// The names of the priorities defined for the context at `path`, from most to least important. A priority's id is its
+/// position among the priorities of its file, priorities with the same id keep their order and those whose id is not a
+/// number come last.
+fn ranked_priorities(sum: &ConfigSum, path: &Path) -> Vec<String> {
+    let mut names: Vec<String> = vec![];
+    for priority in &sum.priorities {
+        let name = &priority.priority.name;
+        if !names.contains(name) && sum.resolve_priority(name, path).is_some() {
+            names.push(name.clone());
+        }
+    }
+    names.sort_by_key(|name| sum.resolve_priority(name, path).and_then(|v| v.id.parse::<usize>().ok()).unwrap_or(usize::MAX));
+    names
+}
+
+fn edit_all(sub_matches: &ArgMatches) {
+    let mut config = chosen_config(sub_matches);
+    let path = config.path.clone().unwrap();
+    let (sum, priorities) = available_priorities(&config);
+
+    let ids: Vec<String> = config.reminders.iter().map(|v| v.id.clone()).collect();
+    let mut document = bulk_edit::render(&sum.display_path(&path), &config.reminders);
+    let file = std::env::temp_dir().join(format!("rmnd-edit-all-{}.txt", std::process::id()));
+
+    let entries = loop {
+        if let Err(error) = fs::write(&file, &document) {
+            panic!("Failed to write {:?}: {:?}", file, error);
+        }
+        if !run_editor(&file) {
+            fs::remove_file(&file).ok();
+            fail("The editor exited with an error, nothing was saved.");
+        }
+        document = fs::read_to_string(&file).unwrap_or_default();
+
+        match bulk_edit::parse(&document, &ids, &priorities) {
+            Ok(entries) => break entries,
+            Err(errors) => {
+                for error in errors {
+                    eprintln!("{} {}", "error:".red(), error);
+                }
+                if !confirm("Nothing was saved, edit again?") {
+                    fs::remove_file(&file).ok();
+                    fail("Nothing was saved.");
+                }
+            },
+        }
+    };
+    fs::remove_file(&file).ok();
+
+    let (reminders, summary) = bulk_edit::apply(&config.reminders, entries, &current_user());
+    if summary == bulk_edit::Summary::default() {
+        println!("No changes.");
+        return;
+    }
+
+    config.reminders = reminders;
+    save_config(&config);
+    println!(
+        "{} added, {} removed, {} changed{} in {}.",
+        summary.added,
+        summary.removed,
+        summary.changed,
+        if summary.reordered { ", reordered" } else { "" },
+        sum.display_path(&path)
+    );
+}
+
+fn import(sub_matches: &ArgMatches) {
+    let format = sub_matches.get_one::<String>("from").unwrap();
+    let file = sub_matches.get_one::<String>("file").unwrap();
+    let dry_run = *sub_matches.get_one::<bool>("dry-run").unwrap_or(&false);
+
+    let contents = fs::read_to_string(file).unwrap_or_else(|e| fail(&format!("Failed to read {}: {}", file, e)));
+    let items = import::parse(format, &contents).unwrap_or_else(|e| fail(&e));
+
+    let mut config = chosen_config(sub_matches);
+    let path = config.path.clone().unwrap();
+    let (sum, priorities) = available_priorities(&config);
+    if priorities.is_empty() {
+        fail(&format!("No priorities are defined for {}.", sum.display_path(&path)));
+    }
+
+    // The file's own table overrides the global one, and --map both.
+    let mut map = load_global_config().settings.import_priorities;
+    map.extend(config.settings.import_priorities.clone());
+    for mapping in sub_matches.get_many::<String>("map").into_iter().flatten() {
+        let Some((from, to)) = mapping.split_once('=') else {
+            fail(&format!("Invalid mapping {}, expected FROM=PRIORITY.", mapping));
+        };
+        map.insert(from.to_string(), to.to_string());
+    }
+    for to in map.values() {
+        if !priorities.contains(to) {
+            fail(&format!("Priorities are mapped to {}, which is not defined for {}.", to, sum.display_path(&path)));
+        }
+    }
+    let fallback = match sub_matches.get_one::<String>("priority") {
+        Some(name) => find_priority(name.clone(), &path).name,
+        None => priorities.last().unwrap().clone(),
+    };
+
+    let mut texts: Vec<String> = config.reminders.iter().map(|v| v.text.trim().to_lowercase()).collect();
+    let author = current_user();
+    let (mut added, mut updated, mut skipped) = (0, 0, 0);
+    for mut item in items {
+        let priority = item
+            .priority
+            .as_ref()
+            .and_then(|v| map.iter().find(|(from, _)| from.eq_ignore_ascii_case(v)))
+            .map(|(_, to)| to.clone())
+            .or_else(|| item.rank.map(|v| priorities[v.min(priorities.len() - 1)].clone()))
+            .unwrap_or_else(|| fallback.clone());
+        // Calendars list the priority among the categories, see `rmnd export`.
+        item.tags.retain(|v| !v.trim_start_matches(['+', '@']).eq_ignore_ascii_case(&priority));
+
+        let mut details = vec![];
+        if let Some(due) = item.due {
+            details.push(format!("due {}", due));
+        }
+        if item.done.is_some() {
+            details.push("done".to_string());
+        }
+        details.extend(item.tags.iter().cloned());
+        let details = if details.is_empty() { "".to_string() } else { format!(" ({})", details.join(", ")) };
+
+        let done_at = item.done.map(|v| v.unwrap_or_else(checklist::now));
+        let existing = item.uid.as_ref().and_then(|uid| config.reminders.iter().position(|v| v.uid.as_ref() == Some(uid)));
+        if let Some(index) = existing {
+            let reminder = &mut config.reminders[index];
+            let mut changed = reminder.clone();
+            changed.priority = priority.clone();
+            changed.text = item.text.clone();
+            changed.tags = item.tags;
+            changed.due = item.due;
+            changed.snoozed_until = item.snoozed_until;
+            // Keep when it was marked done here if the calendar does not say.
+            changed.done_at = match (item.done, reminder.done_at) {
+                (Some(None), Some(done_at)) => Some(done_at),
+                _ => done_at,
+            };
+
+            if changed == *reminder {
+                skipped += 1;
+                if dry_run {
+                    println!("{} {}: {}{}", "=".dimmed(), priority, item.text, format!("{} unchanged", details).dimmed());
+                }
+                continue;
+            }
+            updated += 1;
+            if dry_run {
+                println!("{} {}: {}{}", "~".yellow(), priority, item.text, details.dimmed());
+            } else {
+                *reminder = changed;
+            }
+            continue;
+        }
+
+        let key = item.text.trim().to_lowercase();
+        if texts.contains(&key) {
+            skipped += 1;
+            if dry_run {
+                println!("{} {}: {}{}", "=".dimmed(), priority, item.text, format!("{} already exists", details).dimmed());
+            }
+            continue;
+        }
+        texts.push(key);
+        added += 1;
+
+        if dry_run {
+            println!("{} {}: {}{}", "+".green(), priority, item.text, details.dimmed());
+            continue;
+        }
+
+        config.reminders.push(Reminder {
+            priority,
+            author: author.clone(),
+            text: item.text,
+            tags: item.tags,
+            due: item.due,
+            uid: item.uid,
+            done_at,
+            snoozed_until: item.snoozed_until,
+            ..Default::default()
+        });
+    }
 
-        let mut config;
-        if global {
-            config = load_global_config();
+    let context = sum.display_path(&path);
+    if dry_run {
+        println!("Would import {} reminder(s) into {} and update {}, skipping {} already there.", added, context, updated, skipped);
+        return;
+    }
+    if added > 0 || updated > 0 {
+        save_config(&config);
+    }
+    println!("Imported {} reminder(s) into {} and updated {}, skipped {} already there.", added, context, updated, skipped);
+}
+
+fn export(sub_matches: &ArgMatches) {
+    let format = sub_matches.get_one::<String>("to").unwrap();
+    let include_done = *sub_matches.get_one::<bool>("done").unwrap_or(&false);
+    let (sum, mut scope) = select(sub_matches);
+
+    // Snoozed reminders are exported too, calendars show when they come back. Without short circuiting every condition
+    // is evaluated, so leaving out these checks is enough to include them.
+    let ignored = |check: &Check| check.condition.starts_with("snoozed until") || (include_done && check.condition == "done");
+    let selected: Vec<&LocalReminder> = sum
+        .reminders
+        .iter()
+        .filter(|v| scope.checks(v, false).iter().filter(|c| !ignored(c)).all(|c| c.holds))
+        .collect();
+
+    let mut names: Vec<&String> = vec![];
+    for priority in &sum.priorities {
+        if !names.contains(&&priority.priority.name) {
+            names.push(&priority.priority.name);
+        }
+    }
+    let entries: Vec<export::Entry> = selected
+        .iter()
+        .map(|v| export::Entry {
+            context: sum.display_path(&v.path),
+            reminder: &v.reminder,
+            priority: sum.resolve_priority(&v.reminder.priority, &v.path),
+            rank: names.iter().position(|name| **name == v.reminder.priority),
+        })
+        .collect();
+
+    if format == "ics" {
+        let undated = selected.iter().filter(|v| !export::dated(&v.reminder)).count();
+        if undated > 0 {
+            warn(&format!("Left out {} reminder(s) without a due date, reminder time or snooze, calendars only hold dated ones.", undated));
+        }
+    }
+
+    let document = export::render(format, &entries, names.len());
+    match sub_matches.get_one::<String>("output") {
+        Some(output) => {
+            fs::write(output, document).unwrap_or_else(|e| fail(&format!("Failed to write {}: {}", output, e)));
+            println!("Exported {} reminder(s) to {}.", entries.len(), output);
+        },
+        None => print!("{}", document),
+    }
+}
+
+fn convert(sub_matches: &ArgMatches) {
+    let format = Format::from_name(sub_matches.get_one::<String>("to").unwrap()).unwrap();
+    let mut config = chosen_config(sub_matches);
+    let from = config.path.clone().unwrap();
+    if config.pathless || from == global_config_path() {
+        fail("This context is stored in the global config, which is always toml.");
+    }
+    if Format::of(&from) == format {
+        println!("{} is already {}.", from.to_str().unwrap(), format.extension());
+        return;
+    }
+
+    let to = from.with_file_name(format!("rmnd.{}", format.extension()));
+    if to.exists() {
+        fail(&format!("{} already exists.", to.to_str().unwrap()));
+    }
+    config.path = Some(to.clone());
+    save_config(&config);
+    if let Err(error) = fs::remove_file(&from) {
+        warn(&format!("Failed to remove {}: {}", from.to_str().unwrap(), error));
+    }
+
+    let mut global_config = load_global_config();
+    if let Some(index) = find_registered(&global_config, &from) {
+        global_config.config_paths[index] = to.to_str().unwrap().to_string();
+        save_config(&global_config);
+    }
+    println!("Converted {} to {}.", from.to_str().unwrap(), to.to_str().unwrap());
+}
+
+/// `rmnd merge-driver %O %A %B %P`, exits with 1 when both branches changed something differently.
+fn merge_driver(sub_matches: &ArgMatches) {
+    let base_file = sub_matches.get_one::<String>("base").unwrap();
+    let current_file = sub_matches.get_one::<String>("current").unwrap();
+    let other_file = sub_matches.get_one::<String>("other").unwrap();
+    let path = sub_matches.get_one::<String>("path").unwrap_or(current_file);
+    let format = Format::of(Path::new(path));
+
+    // A file added on both branches has an empty ancestor.
+    let read = |file: &str| -> Option<(String, ConfigFile)> {
+        let text = fs::read_to_string(file).ok()?;
+        let mut config = if text.trim().is_empty() { ConfigFile::default() } else { format.parse(text.as_bytes()).ok()? };
+        config.assign_ids();
+        Some((text, config))
+    };
+    let (Some((_, base)), Some((current_text, current)), Some((_, other))) = (read(base_file), read(current_file), read(other_file)) else {
+        warn(&format!("Not every version of {} could be read, falling back to a line-based merge.", path));
+        let status = std::process::Command::new("git")
+            .args(["merge-file", "-L", "current", "-L", "base", "-L", "other", current_file, base_file, other_file])
+            .status();
+        std::process::exit(status.ok().and_then(|v| v.code()).map(|v| v.clamp(0, 1)).unwrap_or(2));
+    };
+
+    let (merged, conflicts) = merge::context_files(&base, &current, &other);
+    if let Err(error) = fs::write(current_file, format.write_over(&merged, Some(&current_text))) {
+        panic!("Failed to write {}: {:?}", current_file, error);
+    }
+    if conflicts.is_empty() {
+        return;
+    }
+    for (what, fields) in conflicts {
+        if fields.is_empty() {
+            warn(&format!("{} in {} was removed on one branch and changed on the other, it was kept.", what, path));
         } else {
-            config = load_local_config();
+            warn(&format!("Both branches changed {} of {} in {}, the current branch's values were kept.", fields.join(", "), what, path));
         }
+    }
+    std::process::exit(1);
 }
 
-fn remove(sub_matches: &ArgMatches) {
-    println!("Removing...");
+/// Who is running rmnd, from the global settings, git's user.name or the login name, in that order.
+fn current_user() -> String {
+    let global_config = load_global_config();
+    if let Some(username) = global_config.settings.username {
+        return username;
+    }
+
+    let output = std::process::Command::new("git").args(["config", "user.name"]).output();
+    if let Ok(output) = output {
+        let name = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if output.status.success() && !name.is_empty() {
+            return name;
+        }
+    }
+
+    std::env::var("USER").or_else(|_| std::env::var("USERNAME")).unwrap_or_else(|_| "unknown".to_string())
 }
 
-fn prio(sub_matches: &ArgMatches) {
-    println!("Priorities...");
+fn find_checklist<'a>(config: &'a mut ConfigFile, name: &str) -> &'a mut Checklist {
+    let path = config.path.clone().unwrap();
+    match config.checklists.iter_mut().find(|v| v.name == name) {
+        Some(checklist) => checklist,
+        None => panic!("No checklist named {} in {:?}.", name, path),
+    }
+}
+
+fn checklist(sub_matches: &ArgMatches) {
+    let global: bool = *sub_matches.get_one("global").unwrap_or(&false);
+    let mut config = if global { load_global_config() } else { load_local_config() };
+
+    match sub_matches.subcommand() {
+        Some(("add", sub_matches)) => {
+            let name = sub_matches.get_one::<String>("name").unwrap();
+            let steps: Vec<String> = sub_matches.get_many::<String>("steps").unwrap().cloned().collect();
+
+            match config.checklists.iter_mut().find(|v| v.name == *name) {
+                Some(checklist) => {
+                    checklist.steps.extend(steps);
+                    println!("Checklist {} now has {} steps.", name, checklist.steps.len());
+                },
+                None => {
+                    println!("Created checklist {} with {} steps.", name, steps.len());
+                    config.checklists.push(Checklist { name: name.clone(), steps, runs: vec![] });
+                },
+            }
+            save_config(&config);
+        },
+        Some(("run", sub_matches)) => {
+            let name = sub_matches.get_one::<String>("name").unwrap();
+            let restart = *sub_matches.get_one::<bool>("restart").unwrap_or(&false);
+            let user = current_user();
+
+            let checklist = find_checklist(&mut config, name);
+            if restart {
+                if let Some(run) = checklist.current_run() {
+                    run.finished_at = Some(checklist::now());
+                }
+            }
+
+            match checklist.current_run() {
+                Some(run) => println!(
+                    "Resuming the run of {} started by {} at {}.",
+                    name,
+                    run.started_by,
+                    run.started_at.format("%Y-%m-%d %H:%M")
+                ),
+                None => checklist.runs.push(ChecklistRun::new(user.clone())),
+            }
+
+            let total = find_checklist(&mut config, name).steps.len();
+            for index in 0..total {
+                let checklist = find_checklist(&mut config, name);
+                let step = checklist.steps[index].clone();
+                if checklist.current_run().unwrap().checked(index, &step).is_some() {
+                    continue;
+                }
+
+                let answer = loop {
+                    println!("[{}/{}] {} ([d]one, [s]kip, [q]uit)", index + 1, total, step.bold());
+                    let Ok(input) = try_read!() else {
+                        break 'q';
+                    };
+                    let input: char = input;
+                    match input.to_ascii_lowercase() {
+                        answer @ ('d' | 's' | 'q') => break answer,
+                        _ => continue,
+                    }
+                };
+
+                if answer == 'q' {
+                    println!("Stopped, run `rmnd checklist run {}` to resume.", name);
+                    return;
+                }
+
+                checklist.current_run().unwrap().checked.push(CheckedStep {
+                    step,
+                    index: Some(index),
+                    by: user.clone(),
+                    at: checklist::now(),
+                    skipped: answer == 's',
+                });
+                // Saved after every step so an interrupted run can be resumed.
+                save_config(&config);
+            }
+
+            let checklist = find_checklist(&mut config, name);
+            let run = checklist.current_run().unwrap();
+            run.finished_at = Some(checklist::now());
+            let skipped = run.checked.iter().filter(|v| v.skipped).count();
+            save_config(&config);
+
+            if skipped > 0 {
+                println!("Finished {} with {} skipped step(s).", name, skipped);
+            } else {
+                println!("Finished {}.", name);
+            }
+        },
+        Some(("list", sub_matches)) => {
+            if let Some(name) = sub_matches.get_one::<String>("name") {
+                let checklist = find_checklist(&mut config, name);
+                let run = checklist.runs.last();
+                for (index, step) in checklist.steps.iter().enumerate() {
+                    match run.and_then(|v| v.checked(index, step)) {
+                        Some(checked) => {
+                            let mark = if checked.skipped { "skipped".yellow() } else { "done".green() };
+                            println!("{}. {} ({} by {} at {})", index + 1, step, mark, checked.by, checked.at.format("%Y-%m-%d %H:%M"));
+                        },
+                        None => println!("{}. {}", index + 1, step),
+                    }
+                }
+                return;
+            }
+
+            for checklist in &config.checklists {
+                let status = match checklist.runs.last() {
+                    Some(run) if run.finished_at.is_none() => {
+                        format!("in progress, {}/{} checked", run.checked.len(), checklist.steps.len())
+                    },
+                    Some(run) => format!("last finished {}", run.finished_at.unwrap().format("%Y-%m-%d %H:%M")),
+                    None => "never run".to_string(),
+                };
+                println!("{} ({} steps, {})", checklist.name, checklist.steps.len(), status);
+            }
+        },
+        Some(("remove", sub_matches)) => {
+            let name = sub_matches.get_one::<String>("name").unwrap();
+            let Some(index) = config.checklists.iter().position(|v| v.name == *name) else {
+                panic!("No checklist named {} in {:?}.", name, config.path.unwrap());
+            };
+            config.checklists.remove(index);
+            save_config(&config);
+            println!("Removed checklist {}.", name);
+        },
+        _ => unreachable!(),
+    }
+}
+
+/// Scans the directories of the local contexts in `sum` for marker comments, each file belongs to the closest context
+/// containing it. The found markers are returned as virtual reminders that are not stored anywhere.
+fn scan_contexts(sum: &ConfigSum) -> Vec<LocalReminder> {
+    let global_config = load_global_config();
+    let all_contexts: Vec<PathBuf> = get_all().contexts.iter().map(|v| v.parent().unwrap().to_path_buf()).collect();
+
+    let mut result = vec![];
+    for path in &sum.contexts {
+        let dir = path.parent().unwrap();
+        let settings = load_context(path).settings;
+
+        let markers = settings
+            .markers
+            .or_else(|| global_config.settings.markers.clone())
+            .unwrap_or_else(|| scan::DEFAULT_MARKERS.iter().map(|v| v.to_string()).collect());
+        let mut priorities = global_config.settings.marker_priorities.clone();
+        priorities.extend(settings.marker_priorities);
+
+        // Markers without a mapping get the least important priority, tags and marker names are rarely defined ones.
+        let fallback = ranked_priorities(sum, path).pop().unwrap_or_default();
+
+        let nested: Vec<PathBuf> = all_contexts.iter().filter(|v| v.starts_with(dir) && *v != dir).cloned().collect();
+        for found in scan::scan(dir, &markers, &nested) {
+            let reminder = Reminder { priority: found.priority(&priorities, &fallback), text: found.text.clone(), ..Default::default() };
+            let mut local = LocalReminder::new(reminder, path.clone());
+            local.source = Some((found.file, found.line));
+            result.push(local);
+        }
+    }
+
+    result
+}
+
+fn scan_command(sub_matches: &ArgMatches) {
+    let all = if *sub_matches.get_one::<bool>("all").unwrap_or(&false) { get_all() } else { get_local() };
+    let import = *sub_matches.get_one::<bool>("import").unwrap_or(&false);
+    let scanned = scan_contexts(&all);
+
+    for group in scanned.chunk_by(|a, b| a.path == b.path) {
+        let path = &group[0].path;
+        println!("{}", all.display_path(path));
+        for reminder in group {
+            let (file, line) = reminder.source.as_ref().unwrap();
+            let file = file.strip_prefix(path.parent().unwrap()).unwrap_or(file);
+            let priority = match all.resolve_priority(&reminder.reminder.priority, path) {
+                Some(priority) => reminder.reminder.priority.color(priority.color),
+                None => reminder.reminder.priority.yellow(),
+            };
+            println!("{}:{}: {}: {}", file.to_str().unwrap(), line, priority, reminder.reminder.text);
+        }
+
+        if import {
+            let mut config = load_context(path);
+            let mut added = 0;
+            for reminder in group {
+                let (file, _) = reminder.source.as_ref().unwrap();
+                let file = file.strip_prefix(path.parent().unwrap()).unwrap_or(file);
+                // Line numbers drift as the file changes, so only the file is kept.
+                let text = format!("{} ({})", reminder.reminder.text, file.to_str().unwrap());
+                if config.reminders.iter().any(|v| v.text == text) {
+                    continue;
+                }
+                config.reminders.push(Reminder { text, ..reminder.reminder.clone() });
+                added += 1;
+            }
+
+            if added > 0 {
+                save_config(&config);
+            }
+            println!("Imported {} reminder(s) into {}.", added, all.display_path(path));
+        }
+    }
+
+    if scanned.is_empty() {
+        println!("No markers found.");
+    }
+}
+
+fn check(sub_matches: &ArgMatches) {
+    let all = get_all();
+    let interactive = *sub_matches.get_one::<bool>("interactive").unwrap_or(&false);
+
+    let mut mappings: Vec<(String, String)> = vec![];
+    for map in sub_matches.get_many::<String>("map").into_iter().flatten() {
+        match map.split_once('=') {
+            Some((old, new)) => mappings.push((old.trim().to_string(), new.trim().to_string())),
+            None => panic!("Invalid mapping {}, expected old=new.", map),
+        }
+    }
+
+    let orphans: Vec<&LocalReminder> = all.orphans().collect();
+    if orphans.is_empty() {
+        println!("All reminders reference a defined priority.");
+        return;
+    }
+
+    for group in orphans.chunk_by(|a, b| a.path == b.path) {
+        println!("{}", all.display_path(&group[0].path));
+        for reminder in group {
+            println!("    {} {}", reminder.reminder.text, format!("(unknown priority: {})", reminder.reminder.priority).yellow());
+        }
+    }
+
+    if interactive {
+        let mut missing: Vec<&String> = orphans.iter().map(|v| &v.reminder.priority).collect();
+        missing.sort();
+        missing.dedup();
+
+        for name in missing {
+            if mappings.iter().any(|(old, _)| old == name) {
+                continue;
+            }
+
+            println!("Remap reminders with priority {} to (leave empty to skip):", name);
+            // `try_read!` reads an empty line at EOF, which would skip every remaining priority.
+            let mut input = String::new();
+            if !matches!(std::io::stdin().read_line(&mut input), Ok(read) if read > 0) {
+                fail("Input ended, nothing was remapped.");
+            }
+            let input = input.trim();
+            if !input.is_empty() {
+                mappings.push((name.clone(), input.to_string()));
+            }
+        }
+    }
+
+    let mut remaining = orphans.len();
+    let mut paths: Vec<&PathBuf> = orphans.iter().map(|v| &v.path).collect();
+    paths.dedup();
+
+    for path in paths {
+        let mut config = load_context(path);
+        let mut changed = false;
+
+        for reminder in config.reminders.iter_mut() {
+            if all.resolve_priority(&reminder.priority, path).is_some() {
+                continue;
+            }
+
+            if let Some((_, new)) = mappings.iter().find(|(old, _)| *old == reminder.priority) {
+                match all.resolve_priority(new, path) {
+                    Some(priority) => {
+                        reminder.priority = priority.name.clone();
+                        changed = true;
+                        remaining -= 1;
+                    },
+                    None => warn(&format!("{} is not defined for {:?}, skipping.", new, path)),
+                }
+            }
+        }
+
+        if changed {
+            save_config(&config);
+        }
+    }
+
+    if remaining > 0 {
+        println!("{} reminder(s) still reference an undefined priority.", remaining);
+        std::process::exit(1);
+    }
+
+    println!("All reminders were remapped.");
 }
 
 fn init(sub_matches: &ArgMatches) {
@@ -499,44 +2587,229 @@ fn init(sub_matches: &ArgMatches) {
 
     let current_dir = current_dir().unwrap().canonicalize().unwrap(); // TODO no unwrap here
 
-    let local = current_dir.join(CONFIG_NAME);
+    let format = sub_matches.get_one::<String>("format").and_then(|v| Format::from_name(v)).unwrap_or(Format::Toml);
+    let local = config_in(&current_dir).unwrap_or_else(|| current_dir.join(format!("rmnd.{}", format.extension())));
     println!("Local is {:?}", local);
 
+    if find_pathless(&global_config, &local).is_some() {
+        println!("A pathless context already exists for this directory.");
+        return;
+    }
+
+    if *sub_matches.get_one::<bool>("no-file").unwrap_or(&false) {
+        if local.is_file() {
+            println!("A local config file already exists, use `rmnd context add` to register it instead.");
+            return;
+        }
+
+        global_config.contexts.push(PathlessContext {
+            directory: current_dir.to_str().unwrap().to_string(),
+            priorities: vec![],
+            reminders: vec![],
+            checklists: vec![],
+            lines: vec![],
+        });
+        save_config(&global_config);
+        println!("Added a pathless context for {:?} to the global config.", current_dir);
+        return;
+    }
+
     for path in &global_config.config_paths {
         let path = Path::new(&path).to_path_buf();
-        if path == local {
+        if path == local && local.is_file() {
             println!("Local config file already exists and is in global config.");
             return;
         }
     }
 
     if local.is_file() {
-        loop {
-            println!("Local config file found that is not in global config, would you like to add it? [y/n]");
-            let input: char = read!();
-            if input.to_lowercase().to_string() == "y" {
-                global_config.config_paths.push(local.to_str().unwrap().to_string());
-
-                // TODO this cannot merge reminders in that you didnt have, store reminders locally as well so it can?
-                break;
-            } else if input.to_lowercase().to_string() == "n" {
-                println!("Nothing to do.");
-                return;
-            }
+        if !confirm("Local config file found that is not in global config, would you like to add it?") {
+            println!("Nothing to do.");
+            return;
         }
+
+        global_config.config_paths.push(local.to_str().unwrap().to_string());
+        save_config(&global_config);
+
+        adopt(read_config(&local));
+        share_or_hide(sub_matches, &local);
     } else {
-        let mut global = load_global_config();
-        global.config_paths.push(local.to_str().unwrap().to_string());
-        let output = toml::to_string_pretty(&global).unwrap();
-        fs::write(&global.path.unwrap(), &output);
+        // The context may still be registered if its file was deleted.
+        if find_registered(&global_config, &local).is_none() {
+            global_config.config_paths.push(local.to_str().unwrap().to_string());
+            save_config(&global_config);
+        }
 
         println!("Writing to {:?}", local);
         let mut local_config = ConfigFile::default_local_config();
-        let output = toml::to_string_pretty(&local_config).unwrap();
-        fs::write(local, &output);
+        local_config.path = Some(local.clone());
+        save_config(&local_config);
+        write_known_copy(&local_config, true);
+        share_or_hide(sub_matches, &local);
     }
 }
 
+/// Applies `init --private|--gitignore|--shared` to a local rmnd.toml inside a git repository, asking when neither was
+/// given.
+fn share_or_hide(sub_matches: &ArgMatches, local: &Path) {
+    let Some(repo) = git::Repo::find(local.parent().unwrap()) else {
+        return;
+    };
+
+    let gitignore = *sub_matches.get_one::<bool>("gitignore").unwrap_or(&false);
+    let private = gitignore || *sub_matches.get_one::<bool>("private").unwrap_or(&false);
+    let shared = *sub_matches.get_one::<bool>("shared").unwrap_or(&false);
+
+    let private = if private || shared {
+        private
+    } else {
+        !confirm("This context is inside a git repository, should the rmnd.toml be shared with the repository?")
+    };
+
+    if !private {
+        println!("Leaving {:?} to be tracked by git.", local);
+        return;
+    }
+
+    if repo.tracking(local) == git::Tracking::Tracked {
+        warn("The rmnd.toml is already tracked by git, ignoring it will not untrack it, use `git rm --cached` for that.");
+    }
+
+    match repo.ignore(local, gitignore) {
+        Some(target) => println!("Added {:?} to {:?}.", local, target),
+        None => println!("{:?} is already ignored.", local),
+    }
+}
+
+/// Brings a freshly registered rmnd.toml (e.g. from a clone) in line with this machine, merging in a previously known
+/// copy of the context and importing priorities it references but that are not defined here.
+fn adopt(mut config: ConfigFile) {
+    // Copies written before reminders had ids get the ones their text derives.
+    let known_copy = |base: bool| {
+        read_known_copy(&config, base).map(|mut v| {
+            v.assign_ids();
+            v
+        })
+    };
+    let known = known_copy(false);
+    let mut changed = false;
+
+    if let Some(ours) = &known {
+        // Merged like `rmnd merge-driver` does, with this file winning the fields both copies changed.
+        let base = known_copy(true).unwrap_or_default();
+        let (reminders, reminder_conflicts) =
+            merge::three_way_fields(&base.reminders, &ours.reminders, &config.reminders, |v| v.id.clone());
+        let (priorities, priority_conflicts) =
+            merge::three_way_fields(&base.priorities, &ours.priorities, &config.priorities, |v| v.name.clone());
+
+        if (reminders != config.reminders || priorities != config.priorities) &&
+            confirm("A previously known copy of this context differs from this file, merge it in?")
+        {
+            for conflict in reminder_conflicts {
+                let text = reminders.iter().find(|v| v.id == conflict.key).map(|v| v.text.clone()).unwrap_or_default();
+                warn(&format!("Both copies changed the reminder {} \"{}\", keeping this file's version.", conflict.key, text));
+            }
+            for conflict in priority_conflicts {
+                warn(&format!("Both copies changed the priority {}, keeping this file's version.", conflict.key));
+            }
+
+            config.reminders = reminders;
+            config.priorities = priorities;
+            changed = true;
+        }
+    }
+
+    let path = config.path.clone().unwrap();
+    let all = get_all();
+    let mut missing: Vec<String> = all
+        .orphans()
+        .filter(|v| v.path == path)
+        .map(|v| v.reminder.priority.clone())
+        .collect();
+    missing.sort();
+    missing.dedup();
+
+    let mut global_config = load_global_config();
+    let mut global_changed = false;
+    for name in missing {
+        println!("The priority {} is used by this context but not defined, import it into the [l]ocal file, the [g]lobal config or [s]kip?", name);
+        // Treat a closed stdin as skipping this and every following priority.
+        let choice: char = loop {
+            let Ok(input) = try_read!() else {
+                break 's';
+            };
+            let input: char = input;
+            match input.to_ascii_lowercase() {
+                'l' | 'g' | 's' => break input.to_ascii_lowercase(),
+                _ => println!("Please answer l, g or s."),
+            }
+        };
+        if choice == 's' {
+            continue;
+        }
+
+        let known_priority = known.as_ref().and_then(|v| v.priorities.iter().find(|p| p.name == name)).cloned();
+        let mut priority = match known_priority {
+            Some(priority) => priority,
+            None => {
+                println!("Color for {} (leave empty for white):", name);
+                let input: String = try_read!("{}\n").unwrap_or_default();
+                let color = match input.trim() {
+                    "" => reminder::Color::White,
+                    color => match color.parse::<colored::Color>() {
+                        Ok(color) => color.into(),
+                        Err(_) => {
+                            warn(&format!("Unknown color {}, using white.", color));
+                            reminder::Color::White
+                        },
+                    },
+                };
+                Priority::new(name, String::new(), color)
+            },
+        };
+
+        if choice == 'l' {
+            priority.id = config.priorities.len().to_string();
+            config.priorities.push(priority);
+            changed = true;
+        } else {
+            priority.id = global_config.priorities.len().to_string();
+            global_config.priorities.push(priority);
+            global_changed = true;
+        }
+    }
+
+    if global_changed {
+        save_config(&global_config);
+    }
+    if changed {
+        save_config(&config);
+        write_known_copy(&config, true);
+    } else if known.is_none() {
+        // Later adoptions merge against these copies.
+        write_known_copy(&config, false);
+        write_known_copy(&config, true);
+    }
+    println!("Added {:?}.", path);
+}
+
+#[cfg(unix)]
+fn daemon_command(sub_matches: &ArgMatches) {
+    if let Some(("status", _)) = sub_matches.subcommand() {
+        daemon::status();
+        return;
+    }
+    let notifier = sub_matches.get_one::<String>("notify").cloned();
+    let due_time = sub_matches.get_one::<NaiveTime>("due-time").copied().unwrap_or_else(|| NaiveTime::from_hms_opt(9, 0, 0).unwrap());
+    daemon::run(notifier, due_time);
+}
+
+/// The daemon is reached over a Unix socket.
+#[cfg(not(unix))]
+fn daemon_command(_: &ArgMatches) {
+    fail("`rmnd daemon` is only available on Unix.");
+}
+
 fn main() {
     let matches = cli().get_matches();
 
@@ -547,6 +2820,20 @@ fn main() {
         | Some(("remove", sub_matches)) => remove(sub_matches),
         | Some(("prio", sub_matches)) => prio(sub_matches),
         | Some(("init", sub_matches)) => init(sub_matches),
+        | Some(("check", sub_matches)) => check(sub_matches),
+        | Some(("context", sub_matches)) => context(sub_matches),
+        | Some(("git", sub_matches)) => git_command(sub_matches),
+        | Some(("exec", sub_matches)) => exec(sub_matches),
+        | Some(("checklist", sub_matches)) => checklist(sub_matches),
+        | Some(("scan", sub_matches)) => scan_command(sub_matches),
+        | Some(("lsp", _)) => lsp::run(),
+        | Some(("edit-all", sub_matches)) => edit_all(sub_matches),
+        | Some(("import", sub_matches)) => import(sub_matches),
+        | Some(("export", sub_matches)) => export(sub_matches),
+        | Some(("convert", sub_matches)) => convert(sub_matches),
+        | Some(("merge-driver", sub_matches)) => merge_driver(sub_matches),
+        | Some(("daemon", sub_matches)) => daemon_command(sub_matches),
+        | Some(("tui", sub_matches)) => tui::run(*sub_matches.get_one::<bool>("all").unwrap_or(&false)),
         | Some((ext, sub_matches)) => {
             let args = sub_matches
                 .get_many::<OsString>("")
@@ -568,6 +2855,10 @@ fn main() {
  *    -p, --priorities
  *    -q, --quotes
  *    -i, --show-ids
+ *    -x, --explain (list hidden reminders and why)
+ *    -f, --for-file <path> (reminders relevant to a file, for editors)
+ *    --scan (include marker comments)
+ *    --format <human|quickfix> (path:line:col: priority: text)
  *    --author (only show the reminders/quotes by a specific author) // TODO NEED TO ADD
  *       option to specify authors for quotes && reminders, how? qauthors, rauthors? regex options for all?
  * add
@@ -577,8 +2868,14 @@ fn main() {
  *       --author
  *    global, --global, -g (global, defaults to contextual)
  *    context // TODO NEED TO ADD
+ *    -b, --branch [glob] (only show on matching branches, defaults to the current branch)
+ *    -e, --event <hook> (show when a git hook runs), --blocking (fail that hook)
+ *    --command <glob> (show on `rmnd exec`), --confirm (ask before running)
+ *    --anchor <path or glob> (only show within it)
+ *    -w, --when <condition> (env:NAME=VALUE, exists:PATH, newer:PATH:7d, cmd:COMMAND, ! to negate)
+ *    --due <date>, --remind-at <time|HH:MM|30m>, --every <interval> (fired by `rmnd daemon`)
  * remind, r (alias for add remind)
- * remove
+ * remove [id or text] (picker when omitted or ambiguous, "did you mean" off a terminal)
  *    --regex (or just accept regex anywhere)
  *    -i
  *    -p
@@ -591,8 +2888,57 @@ fn main() {
  *       -c, --color
  *       -i
  *    remove
+ * context, ctx
+ *    list, ls
+ *    add [path]
+ *    remove, rm <path>
+ *    mv <from> <to>
+ *    prune
+ * git
+ *    install-hooks (pre-commit, pre-push, post-checkout)
+ *       -f, --force
+ *    install-merge-driver (.gitattributes and git config for merge-driver)
+ * merge-driver %O %A %B %P (three-way merge by reminder id, field level conflicts, exit 1 on conflict)
+ * exec -- <command>
+ *    -y, --yes
+ *    --check (show only, for shell hooks)
+ *    --shell-hook <bash|zsh>
+ * checklist, cl
+ *    add <name> <steps>...
+ *    run <name> (resumes an unfinished run)
+ *       --restart
+ *    list, ls [name]
+ *    remove, rm <name>
+ * scan (TODO, FIXME, REMIND(tag) comments, settings.markers, settings.marker_priorities)
+ *    -a, --all
+ *    --import
+ * edit-all (id priority text per line in $EDITOR, - as id adds, saved only if every line parses)
+ *    -g, --global
+ *    -c, --context <path>
+ * import --from <todotxt|taskwarrior|org|markdown|ics> <file> (settings.import_priorities, dedupe by text, ics updates by UID)
+ *    -m, --map FROM=PRIORITY
+ *    -p <priority> (for tasks without one)
+ *    -n, --dry-run
+ *    -g, --global, -c, --context <path>
+ * export --to <todotxt|markdown|org|html|ics> (over the reminders show shows)
+ *    -o, --output <file>
+ *    -d, --done (include done reminders)
+ *    -a, --all, -p, --priorities, -f, --for-file, --scan
+ * convert --to <toml|yaml|json|json5> (rewrites rmnd.toml as rmnd.yaml etc. and re-registers it)
+ *    -g, --global, -c, --context <path>
+ * tui (j/k, / filter, a add, e edit, p/P priority, x done, s snooze, d delete)
+ *    -a, --all
+ * lsp (diagnostics per file, code actions: done, snooze; priority.severity)
+ * daemon (fires due, remind_at and every reminders, reloads changed configs)
+ *    --notify <command> (RMND_* env vars, defaults to settings.notifier), --due-time <HH:MM>
+ *    status (over CONFIG_DIR/rmnd/daemon.sock)
+ * check
+ *    -m, --map old=new
+ *    -i, --interactive
  * init
  *    add rmnd file locally (check if already exists ie git clone, rsync and ask if overrie or keep)
+ *    --private (add to .git/info/exclude), --gitignore (add to .gitignore instead), --shared (leave tracked)
  *    --no-file (can add a context to the global file if you dont want to have to add the file to your git ignore file, if you want the reminders synced, do not use this option)
+ *    --format <toml|yaml|json|json5> (rmnd.yaml etc., detected by extension, saving keeps the format)
  *
  */
@@ -0,0 +1,335 @@
+use std::collections::HashSet;
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Value};
+
+use crate::config_file::ConfigFile;
+
+/// Three-way merge of two edited copies of a list that share a common ancestor, items are matched across the copies
+/// with `key`. Additions from either side are kept, an item removed on one side is removed unless the other side edited
+/// it, and when both sides edited the same item differently `theirs` wins and the key is reported as a conflict.
+///
+/// The result keeps the order of `theirs`, followed by the items only `ours` added.
+pub(crate) fn three_way<T, K, F,>(base: &[T], ours: &[T], theirs: &[T], key: F,) -> (Vec<T,>, Vec<K,>,)
+where
+    T: Clone + PartialEq,
+    K: Eq + std::hash::Hash + Clone,
+    F: Fn(&T,) -> K, {
+    let find = |list: &'_ [T], k: &K| list.iter().find(|v| key(v,) == *k,).cloned();
+
+    let mut merged = vec![];
+    let mut conflicts = vec![];
+    let mut seen = HashSet::new();
+
+    for item in theirs.iter().chain(ours.iter(),) {
+        let k = key(item,);
+        if !seen.insert(k.clone(),) {
+            continue;
+        }
+
+        let base_item = find(base, &k,);
+        let ours_item = find(ours, &k,);
+        let theirs_item = find(theirs, &k,);
+
+        let result = match (base_item, ours_item, theirs_item,) {
+            // Added on one or both sides.
+            | (None, Some(o,), Some(t,),) => {
+                if o != t {
+                    conflicts.push(k,);
+                }
+                Some(t,)
+            },
+            | (None, Some(o,), None,) => Some(o,),
+            | (None, None, Some(t,),) => Some(t,),
+            // Removed on one side, kept only if the other side changed it.
+            | (Some(b,), Some(o,), None,) => {
+                if o == b {
+                    None
+                } else {
+                    conflicts.push(k,);
+                    Some(o,)
+                }
+            },
+            | (Some(b,), None, Some(t,),) => {
+                if t == b {
+                    None
+                } else {
+                    conflicts.push(k,);
+                    Some(t,)
+                }
+            },
+            // Present everywhere, take whichever side changed.
+            | (Some(b,), Some(o,), Some(t,),) => {
+                if o == t || o == b {
+                    Some(t,)
+                } else if t == b {
+                    Some(o,)
+                } else {
+                    conflicts.push(k,);
+                    Some(t,)
+                }
+            },
+            | (_, None, None,) => None,
+        };
+
+        if let Some(result,) = result {
+            merged.push(result,);
+        }
+    }
+
+    (merged, conflicts,)
+}
+
+/// Where both sides changed the same item differently, see `three_way_fields`.
+pub(crate) struct Conflict<K,> {
+    pub(crate) key:    K,
+    /// The fields both sides set to different values, empty when one side removed the item and the other changed it.
+    pub(crate) fields: Vec<String,>,
+}
+
+/// Like `three_way`, but an item both sides changed is merged field by field, so only fields both sides changed
+/// differently conflict. Conflicting fields keep the value of `theirs`.
+pub(crate) fn three_way_fields<T, K, F,>(
+    base: &[T],
+    ours: &[T],
+    theirs: &[T],
+    key: F,
+) -> (Vec<T,>, Vec<Conflict<K,>,>,)
+where
+    T: Clone + PartialEq + Serialize + DeserializeOwned,
+    K: Eq + std::hash::Hash + Clone,
+    F: Fn(&T,) -> K,
+{
+    fn find<'a, T, K: PartialEq,>(list: &'a [T], k: &K, key: &impl Fn(&T,) -> K,) -> Option<&'a T,> {
+        list.iter().find(|v| key(v,) == *k,)
+    }
+    let (mut merged, keys,) = three_way(base, ours, theirs, &key,);
+
+    let mut conflicts = vec![];
+    for k in keys {
+        let (Some(o,), Some(t,),) = (find(ours, &k, &key,), find(theirs, &k, &key,),) else {
+            conflicts.push(Conflict {
+                key:    k,
+                fields: vec![],
+            },);
+            continue;
+        };
+        let (item, fields,) = merge_fields(find(base, &k, &key,), o, t,);
+        if let Some(slot,) = merged.iter_mut().find(|v| key(v,) == k,) {
+            *slot = item;
+        }
+        if !fields.is_empty() {
+            conflicts.push(Conflict {
+                key: k,
+                fields,
+            },);
+        }
+    }
+
+    (merged, conflicts,)
+}
+
+/// Three-way merge of a single item's fields, compared by their serialized values. Returns the merged item and the
+/// fields both sides changed differently, which keep the value of `theirs`. Items that do not serialize to a map are
+/// compared as a whole.
+pub(crate) fn merge_fields<T,>(base: Option<&T,>, ours: &T, theirs: &T,) -> (T, Vec<String,>,)
+where T: Clone + Serialize + DeserializeOwned {
+    let to_map = |item: &T| match serde_json::to_value(item,) {
+        | Ok(Value::Object(map,),) => Some(map,),
+        | _ => None,
+    };
+    let (Some(o,), Some(t,),) = (to_map(ours,), to_map(theirs,),) else {
+        return (theirs.clone(), vec!["value".to_string()],);
+    };
+    let b = base.and_then(to_map,).unwrap_or_default();
+
+    let mut merged = Map::new();
+    let mut conflicts = vec![];
+    let mut seen = HashSet::new();
+    for name in t.keys().chain(o.keys(),) {
+        if !seen.insert(name,) {
+            continue;
+        }
+        let (b, o, t,) = (b.get(name,), o.get(name,), t.get(name,),);
+        let value = if o == t || o == b {
+            t
+        } else if t == b {
+            o
+        } else {
+            conflicts.push(name.clone(),);
+            t
+        };
+        if let Some(value,) = value {
+            merged.insert(name.clone(), value.clone(),);
+        }
+    }
+
+    match serde_json::from_value(Value::Object(merged,),) {
+        | Ok(item,) => (item, conflicts,),
+        | Err(_,) => (theirs.clone(), vec!["value".to_string()],),
+    }
+}
+
+/// Merges two branches' versions of a context file for `rmnd merge-driver`. `current` is passed as `theirs`, so it wins
+/// conflicts and its order is kept. Returns the merged file and what conflicted with the fields both sides changed, the
+/// merge fails if there is any.
+pub(crate) fn context_files(
+    base: &ConfigFile,
+    current: &ConfigFile,
+    other: &ConfigFile,
+) -> (ConfigFile, Vec<(String, Vec<String,>,),>,) {
+    let mut merged = current.clone();
+    let (reminders, reminder_conflicts,) =
+        three_way_fields(&base.reminders, &other.reminders, &current.reminders, |v| v.id.clone(),);
+    let (priorities, priority_conflicts,) =
+        three_way_fields(&base.priorities, &other.priorities, &current.priorities, |v| {
+            v.name.clone()
+        },);
+    let (checklists, checklist_conflicts,) =
+        three_way_fields(&base.checklists, &other.checklists, &current.checklists, |v| {
+            v.name.clone()
+        },);
+    let (settings, setting_conflicts,) = merge_fields(Some(&base.settings,), &other.settings, &current.settings,);
+    merged.reminders = reminders;
+    merged.priorities = priorities;
+    merged.checklists = checklists;
+    merged.settings = settings;
+    merged.config_paths = three_way(&base.config_paths, &other.config_paths, &current.config_paths, |v| {
+        v.clone()
+    },)
+    .0;
+
+    let mut conflicts = vec![];
+    for conflict in reminder_conflicts {
+        let text = merged
+            .reminders
+            .iter()
+            .find(|v| v.id == conflict.key,)
+            .map(|v| v.text.clone(),)
+            .unwrap_or_default();
+        conflicts.push((format!("reminder {} \"{}\"", conflict.key, text), conflict.fields,),);
+    }
+    for conflict in priority_conflicts {
+        conflicts.push((format!("priority {}", conflict.key), conflict.fields,),);
+    }
+    for conflict in checklist_conflicts {
+        conflicts.push((format!("checklist {}", conflict.key), conflict.fields,),);
+    }
+    if !setting_conflicts.is_empty() {
+        conflicts.push(("settings".to_string(), setting_conflicts,),);
+    }
+    (merged, conflicts,)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reminder::Reminder;
+
+    fn reminder(id: &str, priority: &str, text: &str,) -> Reminder {
+        Reminder {
+            id: id.to_string(),
+            priority: priority.to_string(),
+            text: text.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn config(reminders: Vec<Reminder,>,) -> ConfigFile {
+        ConfigFile {
+            reminders,
+            ..Default::default()
+        }
+    }
+
+    fn texts(config: &ConfigFile,) -> Vec<&str,> {
+        config.reminders.iter().map(|v| v.text.as_str(),).collect()
+    }
+
+    #[test]
+    fn unchanged_base() {
+        let base = config(vec![reminder("a", "Low", "one",), reminder("b", "Low", "two",)],);
+        let (merged, conflicts,) = context_files(&base, &base, &base,);
+        assert!(merged.reminders == base.reminders);
+        assert!(conflicts.is_empty());
+    }
+
+    #[test]
+    fn one_side_changed() {
+        let base = config(vec![reminder("a", "Low", "one",)],);
+        let other = config(vec![reminder("a", "Low", "one, edited",)],);
+        let (merged, conflicts,) = context_files(&base, &base, &other,);
+        assert_eq!(texts(&merged), ["one, edited"]);
+        assert!(conflicts.is_empty());
+    }
+
+    #[test]
+    fn concurrent_adds() {
+        let base = config(vec![reminder("a", "Low", "one",)],);
+        let current = config(vec![reminder("a", "Low", "one",), reminder("b", "Low", "two",)],);
+        let other = config(vec![reminder("a", "Low", "one",), reminder("c", "Low", "three",)],);
+        let (merged, conflicts,) = context_files(&base, &current, &other,);
+        assert_eq!(texts(&merged), ["one", "two", "three"]);
+        assert!(conflicts.is_empty());
+    }
+
+    #[test]
+    fn same_item_added_differently() {
+        let base = config(vec![],);
+        let current = config(vec![reminder("a", "Low", "mine",)],);
+        let other = config(vec![reminder("a", "High", "theirs",)],);
+        let (merged, conflicts,) = context_files(&base, &current, &other,);
+        assert_eq!(texts(&merged), ["mine"]);
+        assert_eq!(conflicts.len(), 1);
+    }
+
+    #[test]
+    fn edit_against_delete() {
+        let base = config(vec![reminder("a", "Low", "one",), reminder("b", "Low", "two",)],);
+        // Deleted on the current branch, edited on the other: kept with the edit and reported.
+        let current = config(vec![reminder("b", "Low", "two",)],);
+        let other = config(vec![reminder("a", "Low", "one, edited",), reminder("b", "Low", "two",)],);
+        let (merged, conflicts,) = context_files(&base, &current, &other,);
+        assert_eq!(texts(&merged), ["two", "one, edited"]);
+        assert_eq!(conflicts.len(), 1);
+        assert!(conflicts[0].1.is_empty());
+
+        // Deleted on one side and untouched on the other: removed without a conflict.
+        let other = config(vec![reminder("a", "Low", "one",), reminder("b", "Low", "two",)],);
+        let (merged, conflicts,) = context_files(&base, &current, &other,);
+        assert_eq!(texts(&merged), ["two"]);
+        assert!(conflicts.is_empty());
+    }
+
+    #[test]
+    fn different_fields_changed_on_both_sides() {
+        let base = config(vec![reminder("a", "Low", "one",)],);
+        let current = config(vec![reminder("a", "High", "one",)],);
+        let other = config(vec![reminder("a", "Low", "one, edited",)],);
+        let (merged, conflicts,) = context_files(&base, &current, &other,);
+        assert_eq!(merged.reminders[0].priority, "High");
+        assert_eq!(merged.reminders[0].text, "one, edited");
+        assert!(conflicts.is_empty());
+    }
+
+    #[test]
+    fn same_field_changed_on_both_sides() {
+        let base = config(vec![reminder("a", "Low", "one",)],);
+        let current = config(vec![reminder("a", "Low", "mine",)],);
+        let other = config(vec![reminder("a", "High", "theirs",)],);
+        let (merged, conflicts,) = context_files(&base, &current, &other,);
+        // The current branch wins the conflicting field, the other branch's priority merges cleanly.
+        assert_eq!(merged.reminders[0].text, "mine");
+        assert_eq!(merged.reminders[0].priority, "High");
+        assert_eq!(conflicts, [("reminder a \"mine\"".to_string(), vec![
+            "text".to_string()
+        ])]);
+    }
+
+    #[test]
+    fn three_way_keeps_order_of_theirs() {
+        let (merged, conflicts,) = three_way(&[1, 2, 3,], &[1, 2, 3, 4,], &[3, 2, 1,], |v| *v,);
+        assert_eq!(merged, [3, 2, 1, 4]);
+        assert!(conflicts.is_empty());
+    }
+}
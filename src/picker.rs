@@ -0,0 +1,143 @@
+use std::io::{self, IsTerminal};
+
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    terminal,
+};
+use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout},
+    style::{Modifier, Style},
+    text::Line,
+    widgets::{List, ListItem, ListState, Paragraph},
+    Terminal,
+    TerminalOptions,
+    Viewport,
+};
+
+/// How many candidates the picker shows at once.
+const HEIGHT: usize = 8;
+
+/// Whether a picker can be shown, both input and output have to be a terminal.
+pub(crate) fn interactive() -> bool {
+    io::stdin().is_terminal() && io::stdout().is_terminal()
+}
+
+/// The indexes of `labels` matching `query`, best first. Labels that contain the query's characters in order rank
+/// above those only a typo or two away from one of their words, everything else is left out.
+pub(crate) fn rank(query: &str, labels: &[String]) -> Vec<usize> {
+    if query.is_empty() {
+        return (0..labels.len()).collect();
+    }
+
+    let matcher = SkimMatcherV2::default().ignore_case();
+    let query_lower = query.to_lowercase();
+    let mut scored: Vec<(i64, usize)> = labels
+        .iter()
+        .enumerate()
+        .filter_map(|(index, label)| {
+            if let Some(score) = matcher.fuzzy_match(label, query) {
+                return Some((score, index));
+            }
+            let distance = label.split_whitespace().map(|v| edit_distance(&v.to_lowercase(), &query_lower)).min()?;
+            (distance <= query.chars().count() / 3 + 1).then_some((-(distance as i64), index))
+        })
+        .collect();
+
+    // The sort is stable, equally good matches keep their order.
+    scored.sort_by_key(|v| std::cmp::Reverse(v.0));
+    scored.into_iter().map(|(_, index)| index).collect()
+}
+
+/// The Levenshtein distance between two strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + if a == *b { 0 } else { 1 };
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// A one line message listing the best matches for `query`, for when no picker can be shown.
+pub(crate) fn did_you_mean(what: &str, query: &str, labels: &[String]) -> String {
+    let ranked = rank(query, labels);
+    if ranked.is_empty() {
+        return format!("No {} matches \"{}\".", what, query);
+    }
+
+    let suggestions: Vec<String> = ranked.iter().take(5).map(|v| format!("\"{}\"", labels[*v])).collect();
+    format!("No single {} matches \"{}\", did you mean {}?", what, query, suggestions.join(", "))
+}
+
+/// Shows a filter-as-you-type list of `labels` below the cursor, starting from `query`. Returns the index of the picked
+/// label, or `None` when the picker was cancelled.
+pub(crate) fn pick(prompt: &str, labels: &[String], query: &str) -> Option<usize> {
+    let mut query = query.to_string();
+    let mut state = ListState::default();
+    state.select(Some(0));
+
+    terminal::enable_raw_mode().ok()?;
+    let options = TerminalOptions { viewport: Viewport::Inline(HEIGHT as u16 + 1) };
+    let Ok(mut terminal) = Terminal::with_options(CrosstermBackend::new(io::stdout()), options) else {
+        terminal::disable_raw_mode().ok();
+        return None;
+    };
+
+    let picked = loop {
+        let ranked = rank(&query, labels);
+        let selected = state.selected().unwrap_or(0).min(ranked.len().saturating_sub(1));
+        state.select(Some(selected));
+
+        let drawn = terminal.draw(|frame| {
+            let areas = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(1), Constraint::Min(1)])
+                .split(frame.size());
+            frame.render_widget(Paragraph::new(Line::from(format!("{} > {}", prompt, query))), areas[0]);
+
+            let items: Vec<ListItem> = ranked.iter().map(|v| ListItem::new(format!("  {}", labels[*v]))).collect();
+            let list = List::new(items).highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+            frame.render_stateful_widget(list, areas[1], &mut state);
+        });
+        if drawn.is_err() {
+            break None;
+        }
+
+        let Ok(Event::Key(key)) = event::read() else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Enter => break ranked.get(selected).copied(),
+            KeyCode::Esc => break None,
+            KeyCode::Char('c') if control => break None,
+            KeyCode::Up => state.select(Some(selected.saturating_sub(1))),
+            KeyCode::Char('p') if control => state.select(Some(selected.saturating_sub(1))),
+            KeyCode::Down => state.select(Some(selected + 1)),
+            KeyCode::Char('n') if control => state.select(Some(selected + 1)),
+            KeyCode::Backspace => {
+                query.pop();
+                state.select(Some(0));
+            },
+            KeyCode::Char(c) => {
+                query.push(c);
+                state.select(Some(0));
+            },
+            _ => {},
+        }
+    };
+
+    terminal.clear().ok();
+    terminal::disable_raw_mode().ok();
+    picked
+}
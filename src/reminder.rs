@@ -1,8 +1,10 @@
-use std::path::PathBuf;
+use std::{collections::HashSet, path::PathBuf};
 
+use chrono::{DateTime, Local, NaiveDate};
 use serde_derive::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Clone, Copy,)]
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq,)]
+#[allow(clippy::enum_variant_names)]
 pub(crate) enum Color {
     Black,
     Red,
@@ -27,13 +29,27 @@ pub(crate) enum Color {
     },
 }
 
-#[derive(Serialize, Deserialize, Clone,)]
+/// How an editor shows reminders of a priority, see `rmnd lsp`.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq,)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Severity {
+    Hint,
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq,)]
 pub(crate) struct Priority {
-    pub(crate) name:  String,
-    pub(crate) id:    String,
-    pub(crate) color: Color,
+    pub(crate) name:     String,
+    pub(crate) id:       String,
+    pub(crate) color:    Color,
+    /// Defaults to a warning for red and yellow priorities and to info otherwise.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) severity: Option<Severity,>,
 }
 
+#[allow(dead_code)]
 #[derive(Serialize, Deserialize, Clone,)]
 pub(crate) struct Author {
     pub(crate) username: String,
@@ -41,22 +57,115 @@ pub(crate) struct Author {
     pub(crate) name:     String,
 }
 
-#[derive(Serialize, Deserialize, Clone,)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Default,)]
 pub(crate) struct Reminder {
+    /// Identifies the reminder within its file, see `Reminder::derive_id`.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub(crate) id:       String,
     pub(crate) priority: String,
     pub(crate) author:   String,
     pub(crate) text:     String,
+    /// Only show this reminder while a branch matching this glob is checked out in the context's repository.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) branch:   Option<String,>,
+    /// Git hook events this reminder is shown for, e.g. `pre-commit` or `pre-push`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) events:   Vec<String,>,
+    /// Fails the git hooks in `events` for as long as this reminder exists.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) blocking: bool,
+    /// Shown by `rmnd exec` before running a command line matching this glob, e.g. `cargo publish*`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) command:  Option<String,>,
+    /// Ask for confirmation before `rmnd exec` runs the matching command.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) confirm:  bool,
+    /// Conditions that must all hold for this reminder to be shown, see `conditions::Condition`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) when:     Vec<String,>,
+    /// Only show this reminder within this path or glob, relative to the context's directory, e.g. `migrations/**`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) anchor:   Option<String,>,
+    /// Labels such as a todo.txt `+project` or `@context`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) tags:     Vec<String,>,
+    /// The day the reminder is due.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) due:      Option<NaiveDate,>,
+    /// When `rmnd daemon` notifies about the reminder.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) remind_at: Option<DateTime<Local,>,>,
+    /// Repeats the notification at this interval from `remind_at`, or from the due day, e.g. `1d` or `2w`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) every:    Option<String,>,
+    /// The UID of the calendar entry this reminder was imported from, importing it again updates the reminder.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) uid:      Option<String,>,
+    /// When the reminder was marked done, done reminders are kept but no longer shown.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) done_at:  Option<DateTime<Local,>,>,
+    /// Hidden until this time.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) snoozed_until: Option<DateTime<Local,>,>,
 }
 
 pub(crate) struct LocalReminder {
     pub(crate) reminder: Reminder,
     pub(crate) path:     PathBuf,
+    /// The file and line of a virtual reminder found by `rmnd scan`, these are not stored in any config.
+    pub(crate) source:   Option<(PathBuf, usize,),>,
+    /// The file and line the reminder is defined on, the global config for pathless contexts.
+    pub(crate) defined_at: Option<(PathBuf, usize,),>,
+}
+
+/// A priority along with the config file that defines it, priorities defined closer to a reminder's context take
+/// precedence over ones with the same name further up the chain.
+pub(crate) struct LocalPriority {
+    pub(crate) priority: Priority,
+    pub(crate) path:     PathBuf,
 }
 
 impl Reminder {
-    pub(crate) fn format(show_id: bool,) -> String {
+    #[allow(dead_code)]
+    pub(crate) fn format(_show_id: bool,) -> String {
         "".to_string()
     }
+
+    /// An id derived from the priority and text, so reminders written before ids existed get the same one every time
+    /// their file is read until it is saved with them. `salt` resolves collisions within a file. This is FNV-1a, the
+    /// std hasher is not guaranteed to stay the same between Rust versions.
+    pub(crate) fn derive_id(&self, salt: u32,) -> String {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        let input = format!("{}\0{}\0{}", self.priority, self.text, salt);
+        for byte in input.bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3,);
+        }
+        format!("{:07x}", hash >> 36)
+    }
+}
+
+/// Gives every reminder without an id one that is unique among `reminders`.
+pub(crate) fn assign_ids(reminders: &mut [Reminder],) {
+    let mut taken: HashSet<String,> = reminders.iter().map(|v| v.id.clone(),).filter(|v| !v.is_empty(),).collect();
+    for reminder in reminders.iter_mut().filter(|v| v.id.is_empty(),) {
+        let mut salt = 0;
+        while !taken.insert(reminder.derive_id(salt,),) {
+            salt += 1;
+        }
+        reminder.id = reminder.derive_id(salt,);
+    }
+}
+
+impl LocalReminder {
+    pub(crate) fn new(reminder: Reminder, path: PathBuf,) -> Self {
+        LocalReminder {
+            reminder,
+            path,
+            source: None,
+            defined_at: None,
+        }
+    }
 }
 
 impl Priority {
@@ -65,6 +174,16 @@ impl Priority {
             name,
             id,
             color,
+            severity: None,
+        }
+    }
+
+    /// The severity set for this priority or the one its color suggests.
+    pub(crate) fn severity(&self,) -> Severity {
+        match (self.severity, self.color,) {
+            | (Some(severity,), _,) => severity,
+            | (None, Color::Red | Color::BrightRed | Color::Yellow | Color::BrightYellow,) => Severity::Warning,
+            | (None, _,) => Severity::Info,
         }
     }
 }
@@ -101,9 +220,9 @@ impl From<colored::Color,> for Color {
     }
 }
 
-impl Into<colored::Color,> for Color {
-    fn into(self,) -> colored::Color {
-        match self {
+impl From<Color,> for colored::Color {
+    fn from(arg: Color,) -> Self {
+        match arg {
             | Color::Black => colored::Color::Black,
             | Color::Red => colored::Color::Red,
             | Color::Green => colored::Color::Green,
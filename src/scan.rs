@@ -0,0 +1,91 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use ignore::WalkBuilder;
+use regex::Regex;
+
+/// Markers looked for when a context's settings do not list any.
+pub(crate) const DEFAULT_MARKERS: [&str; 3] = ["TODO", "FIXME", "REMIND"];
+
+/// A marker comment found in a file, e.g. `// REMIND(high): rotate the keys` has the marker `REMIND`, the tag `high`
+/// and the text `rotate the keys`.
+pub(crate) struct Found {
+    pub(crate) file:   PathBuf,
+    pub(crate) line:   usize,
+    pub(crate) marker: String,
+    pub(crate) tag:    Option<String>,
+    pub(crate) text:   String,
+}
+
+impl Found {
+    /// The priority a marker maps to: the tag, then the marker, looked up in `priorities` (case-insensitively),
+    /// falling back to `fallback`, the least important priority of the context.
+    pub(crate) fn priority(&self, priorities: &BTreeMap<String, String>, fallback: &str) -> String {
+        let lookup = |key: &str| {
+            priorities.iter().find(|(k, _)| k.eq_ignore_ascii_case(key)).map(|(_, v)| v.clone())
+        };
+
+        self.tag
+            .as_deref()
+            .and_then(lookup)
+            .or_else(|| lookup(&self.marker))
+            .unwrap_or_else(|| fallback.to_string())
+    }
+}
+
+/// Builds the regex matching any of `markers` right after a comment leader.
+fn marker_regex(markers: &[String]) -> Regex {
+    let markers: Vec<String> = markers.iter().map(|v| regex::escape(v)).collect();
+    Regex::new(&format!(
+        r"(?://+|#+|--|/\*+|^\s*\*|;+|<!--)\s*({})\b(?:\(([^)]*)\))?\s*:?\s*(.*?)\s*(?:\*/|-->)?\s*$",
+        markers.join("|")
+    ))
+    .unwrap()
+}
+
+/// Walks `dir`, respecting `.gitignore` and friends, and collects marker comments. Directories in `skip` are not
+/// entered, they belong to other contexts nested inside this one.
+pub(crate) fn scan(dir: &Path, markers: &[String], skip: &[PathBuf]) -> Vec<Found> {
+    let regex = marker_regex(markers);
+    let root = dir.to_path_buf();
+    let skip = skip.to_vec();
+
+    let walker = WalkBuilder::new(dir)
+        .require_git(false)
+        .filter_entry(move |entry| entry.path() == root || !skip.iter().any(|v| v == entry.path()))
+        .build();
+
+    let mut found = vec![];
+    for entry in walker.flatten() {
+        if !entry.file_type().map(|v| v.is_file()).unwrap_or(false) {
+            continue;
+        }
+
+        // Binary and non UTF-8 files are skipped.
+        let Ok(contents) = fs::read_to_string(entry.path()) else {
+            continue;
+        };
+
+        for (index, line) in contents.lines().enumerate() {
+            if let Some(captures) = regex.captures(line) {
+                let text = captures[3].trim().to_string();
+                if text.is_empty() {
+                    continue;
+                }
+
+                found.push(Found {
+                    file: entry.path().to_path_buf(),
+                    line: index + 1,
+                    marker: captures[1].to_string(),
+                    tag: captures.get(2).map(|v| v.as_str().trim().to_string()).filter(|v| !v.is_empty()),
+                    text,
+                });
+            }
+        }
+    }
+
+    found
+}
@@ -0,0 +1,449 @@
+use std::{
+    io::{self, Stdout},
+    panic,
+    path::{Path, PathBuf},
+};
+
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind},
+    execute,
+    terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout},
+    style::{Color as TuiColor, Modifier, Style},
+    text::{Line, Span},
+    widgets::{List, ListItem, ListState, Paragraph},
+    Frame,
+    Terminal,
+};
+
+use crate::{
+    checklist,
+    conditions,
+    config_file::ConfigSum,
+    reminder::{Color, Reminder},
+};
+
+const HELP: &str = "j/k move  / filter  a add  e edit  p/P priority  x done  s snooze  d delete  r reload  q quit";
+
+/// A line of the list, reminders are grouped by context and then by priority.
+enum Row {
+    Context(PathBuf),
+    Priority(PathBuf, String),
+    /// An index into `ConfigSum::reminders`.
+    Reminder(usize),
+}
+
+#[derive(PartialEq)]
+enum Mode {
+    Browse,
+    Filter,
+    Add,
+    Edit,
+    Snooze,
+    Delete,
+}
+
+struct App {
+    all: bool,
+    sum: ConfigSum,
+    rows: Vec<Row>,
+    state: ListState,
+    filter: String,
+    mode: Mode,
+    input: String,
+    status: String,
+    quit: bool,
+}
+
+/// Runs the full-screen interface over the reminders of the current context chain, or of every context with `all`.
+/// Done reminders are left out, everything else is edited through the same `load_context` and `save_config` as the
+/// other commands.
+pub(crate) fn run(all: bool) {
+    let mut app = App {
+        all,
+        sum: ConfigSum::new(PathBuf::new()),
+        rows: vec![],
+        state: ListState::default(),
+        filter: String::new(),
+        mode: Mode::Browse,
+        input: String::new(),
+        status: String::new(),
+        quit: false,
+    };
+    app.reload();
+
+    // Leave the terminal usable when saving a config panics.
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        restore();
+        hook(info);
+    }));
+
+    let mut terminal = setup().expect("Failed to set up the terminal.");
+    while !app.quit {
+        if let Err(error) = terminal.draw(|frame| app.draw(frame)) {
+            restore();
+            panic!("Failed to draw: {}", error);
+        }
+
+        match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => app.key(key),
+            Ok(_) => {},
+            Err(error) => {
+                restore();
+                panic!("Failed to read input: {}", error);
+            },
+        }
+    }
+
+    restore();
+}
+
+fn setup() -> io::Result<Terminal<CrosstermBackend<Stdout>>> {
+    terminal::enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen)?;
+    Terminal::new(CrosstermBackend::new(io::stdout()))
+}
+
+fn restore() {
+    let _ = terminal::disable_raw_mode();
+    let _ = execute!(io::stdout(), LeaveAlternateScreen);
+}
+
+fn tui_color(color: Color) -> TuiColor {
+    match color {
+        Color::Black => TuiColor::Black,
+        Color::Red => TuiColor::Red,
+        Color::Green => TuiColor::Green,
+        Color::Yellow => TuiColor::Yellow,
+        Color::Blue => TuiColor::Blue,
+        Color::Magenta => TuiColor::Magenta,
+        Color::Cyan => TuiColor::Cyan,
+        Color::White => TuiColor::Gray,
+        Color::BrightBlack => TuiColor::DarkGray,
+        Color::BrightRed => TuiColor::LightRed,
+        Color::BrightGreen => TuiColor::LightGreen,
+        Color::BrightYellow => TuiColor::LightYellow,
+        Color::BrightBlue => TuiColor::LightBlue,
+        Color::BrightMagenta => TuiColor::LightMagenta,
+        Color::BrightCyan => TuiColor::LightCyan,
+        Color::BrightWhite => TuiColor::White,
+        Color::TrueColor { r, g, b } => TuiColor::Rgb(r, g, b),
+    }
+}
+
+impl App {
+    fn reload(&mut self) {
+        self.sum = if self.all { crate::get_all() } else { crate::get_local() };
+        self.rebuild();
+    }
+
+    fn matches(&self, reminder: &Reminder) -> bool {
+        let filter = self.filter.to_lowercase();
+        reminder.done_at.is_none()
+            && (reminder.text.to_lowercase().contains(&filter) || reminder.priority.to_lowercase().contains(&filter))
+    }
+
+    /// Regroups the rows after the reminders or the filter changed, keeping the selection on the same line if possible.
+    fn rebuild(&mut self) {
+        let mut rows = vec![];
+        let mut contexts: Vec<&Path> = vec![];
+        for reminder in &self.sum.reminders {
+            if reminder.source.is_none() && !contexts.contains(&reminder.path.as_path()) {
+                contexts.push(&reminder.path);
+            }
+        }
+
+        for context in contexts {
+            let indexes: Vec<usize> = (0..self.sum.reminders.len())
+                .filter(|v| self.sum.reminders[*v].path == context && self.matches(&self.sum.reminders[*v].reminder))
+                .collect();
+            if indexes.is_empty() {
+                continue;
+            }
+
+            rows.push(Row::Context(context.to_path_buf()));
+            let mut priorities: Vec<&str> = vec![];
+            for index in &indexes {
+                let priority = self.sum.reminders[*index].reminder.priority.as_str();
+                if !priorities.contains(&priority) {
+                    priorities.push(priority);
+                }
+            }
+            for priority in priorities {
+                rows.push(Row::Priority(context.to_path_buf(), priority.to_string()));
+                for index in &indexes {
+                    if self.sum.reminders[*index].reminder.priority == priority {
+                        rows.push(Row::Reminder(*index));
+                    }
+                }
+            }
+        }
+
+        self.rows = rows;
+        let selected = self.state.selected().unwrap_or(0).min(self.rows.len().saturating_sub(1));
+        self.state.select(Some(selected));
+        self.step(0);
+    }
+
+    /// Moves the selection by `by` reminders, skipping the headers.
+    fn step(&mut self, by: isize) {
+        let reminders: Vec<usize> = (0..self.rows.len()).filter(|v| matches!(self.rows[*v], Row::Reminder(_))).collect();
+        if reminders.is_empty() {
+            self.state.select(None);
+            return;
+        }
+
+        let current = self.state.selected().unwrap_or(0);
+        let position = reminders.iter().position(|v| *v >= current).unwrap_or(reminders.len() - 1) as isize;
+        let position = (position + by).clamp(0, reminders.len() as isize - 1) as usize;
+        self.state.select(Some(reminders[position]));
+    }
+
+    fn selected(&self) -> Option<usize> {
+        match self.rows.get(self.state.selected()?)? {
+            Row::Reminder(index) => Some(*index),
+            _ => None,
+        }
+    }
+
+    fn color(&self, priority: &str, path: &Path) -> Option<TuiColor> {
+        self.sum.resolve_priority(priority, path).map(|v| tui_color(v.color))
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let areas = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(1), Constraint::Length(1)])
+            .split(frame.size());
+
+        let now = chrono::Local::now();
+        let items: Vec<ListItem> = self
+            .rows
+            .iter()
+            .map(|row| match row {
+                Row::Context(path) => {
+                    ListItem::new(Span::styled(self.sum.display_path(path), Style::default().add_modifier(Modifier::BOLD)))
+                },
+                Row::Priority(path, priority) => match self.color(priority, path) {
+                    Some(color) => ListItem::new(Span::styled(format!("  {}", priority), Style::default().fg(color))),
+                    None => ListItem::new(Span::styled(format!("  {} (unknown priority)", priority), Style::default().fg(TuiColor::Yellow))),
+                },
+                Row::Reminder(index) => {
+                    let local = &self.sum.reminders[*index];
+                    let reminder = &local.reminder;
+                    let mut style = Style::default();
+                    if let Some(color) = self.color(&reminder.priority, &local.path) {
+                        style = style.fg(color);
+                    }
+
+                    let mut spans = vec![Span::styled(format!("    {}", reminder.text), style)];
+                    let dimmed = Style::default().add_modifier(Modifier::DIM);
+                    if let Some(until) = reminder.snoozed_until.filter(|v| *v > now) {
+                        spans[0].style = spans[0].style.add_modifier(Modifier::DIM);
+                        spans.push(Span::styled(format!(" (snoozed until {})", until.format("%Y-%m-%d %H:%M")), dimmed));
+                    }
+                    if let Some(anchor) = &reminder.anchor {
+                        spans.push(Span::styled(format!(" [{}]", anchor), dimmed));
+                    }
+                    if let Some(branch) = &reminder.branch {
+                        spans.push(Span::styled(format!(" [branch {}]", branch), dimmed));
+                    }
+                    ListItem::new(Line::from(spans))
+                },
+            })
+            .collect();
+
+        let list = List::new(items).highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, areas[0], &mut self.state);
+
+        let status = match self.mode {
+            Mode::Browse if !self.status.is_empty() => Line::from(self.status.as_str()),
+            Mode::Browse if !self.filter.is_empty() => Line::from(format!("filter: {}  (/ to change, esc in filter to clear)", self.filter)),
+            Mode::Browse => Line::from(HELP),
+            Mode::Filter => Line::from(format!("/{}_", self.filter)),
+            Mode::Add => {
+                let (path, priority) = self.add_target();
+                let style = self.color(&priority, &path).map(|v| Style::default().fg(v)).unwrap_or_default();
+                Line::from(vec![Span::raw("add "), Span::styled(priority, style), Span::raw(format!(": {}_", self.input))])
+            },
+            Mode::Edit => Line::from(format!("edit: {}_", self.input)),
+            Mode::Snooze => Line::from(format!("snooze for (e.g. 2h, 1d, 1w): {}_", self.input)),
+            Mode::Delete => Line::from("delete this reminder? y/n"),
+        };
+        frame.render_widget(Paragraph::new(status), areas[1]);
+    }
+
+    fn key(&mut self, key: KeyEvent) {
+        self.status.clear();
+        match self.mode {
+            Mode::Browse => match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+                KeyCode::Char('j') | KeyCode::Down => self.step(1),
+                KeyCode::Char('k') | KeyCode::Up => self.step(-1),
+                KeyCode::Char('g') | KeyCode::Home => self.step(isize::MIN / 2),
+                KeyCode::Char('G') | KeyCode::End => self.step(isize::MAX / 2),
+                KeyCode::Char('/') => self.mode = Mode::Filter,
+                KeyCode::Char('a') => self.begin(Mode::Add, String::new()),
+                KeyCode::Char('e') => {
+                    if let Some(index) = self.selected() {
+                        self.begin(Mode::Edit, self.sum.reminders[index].reminder.text.clone());
+                    }
+                },
+                KeyCode::Char('s') => {
+                    if self.selected().is_some() {
+                        self.begin(Mode::Snooze, "1d".to_string());
+                    }
+                },
+                KeyCode::Char('d') => {
+                    if self.selected().is_some() {
+                        self.mode = Mode::Delete;
+                    }
+                },
+                KeyCode::Char('p') => self.cycle_priority(1),
+                KeyCode::Char('P') => self.cycle_priority(-1),
+                KeyCode::Char('x') => {
+                    self.update(|v| v.done_at = Some(checklist::now()));
+                },
+                KeyCode::Char('r') => self.reload(),
+                _ => {},
+            },
+            Mode::Filter => {
+                match key.code {
+                    KeyCode::Enter => self.mode = Mode::Browse,
+                    KeyCode::Esc => {
+                        self.filter.clear();
+                        self.mode = Mode::Browse;
+                    },
+                    KeyCode::Backspace => {
+                        self.filter.pop();
+                    },
+                    KeyCode::Char(c) => self.filter.push(c),
+                    _ => {},
+                }
+                self.rebuild();
+            },
+            Mode::Delete => {
+                self.mode = Mode::Browse;
+                if key.code == KeyCode::Char('y') {
+                    self.delete();
+                }
+            },
+            Mode::Add | Mode::Edit | Mode::Snooze => match key.code {
+                KeyCode::Esc => self.mode = Mode::Browse,
+                KeyCode::Backspace => {
+                    self.input.pop();
+                },
+                KeyCode::Char(c) => self.input.push(c),
+                KeyCode::Enter => {
+                    let input = std::mem::take(&mut self.input);
+                    let mode = std::mem::replace(&mut self.mode, Mode::Browse);
+                    if input.trim().is_empty() {
+                        return;
+                    }
+                    match mode {
+                        Mode::Add => self.add(input.trim().to_string()),
+                        Mode::Edit => self.update(|v| v.text = input.trim().to_string()),
+                        _ => match conditions::parse_duration(input.trim()).map(chrono::Duration::from_std) {
+                            Ok(Ok(duration)) => self.update(|v| v.snoozed_until = Some(checklist::now() + duration)),
+                            Ok(Err(error)) => self.status = error.to_string(),
+                            Err(error) => self.status = error,
+                        },
+                    }
+                },
+                _ => {},
+            },
+        }
+    }
+
+    fn begin(&mut self, mode: Mode, input: String) {
+        self.mode = mode;
+        self.input = input;
+    }
+
+    /// New reminders go to the selected reminder's context with its priority, or else to the most local context.
+    fn add_target(&self) -> (PathBuf, String) {
+        if let Some(index) = self.selected() {
+            let local = &self.sum.reminders[index];
+            return (local.path.clone(), local.reminder.priority.clone());
+        }
+
+        let path = crate::find_most_local_config();
+        let priority = self
+            .sum
+            .priorities
+            .iter()
+            .find(|v| self.sum.resolve_priority(&v.priority.name, &path).is_some())
+            .map(|v| v.priority.name.clone())
+            .unwrap_or_else(|| "Critical".to_string());
+        (path, priority)
+    }
+
+    fn add(&mut self, text: String) {
+        let (path, priority) = self.add_target();
+        let mut config = crate::load_context(&path);
+        config.reminders.push(Reminder { priority, author: crate::current_user(), text, ..Default::default() });
+        crate::save_config(&config);
+        self.status = format!("Added to {}.", self.sum.display_path(&path));
+        self.reload();
+    }
+
+    /// Applies `change` to the selected reminder in its config file, found by comparing it with what was loaded.
+    fn update(&mut self, change: impl FnOnce(&mut Reminder)) {
+        let Some(index) = self.selected() else {
+            return;
+        };
+        let local = &self.sum.reminders[index];
+        let mut config = crate::load_context(&local.path);
+        match config.reminders.iter_mut().find(|v| **v == local.reminder) {
+            Some(reminder) => {
+                change(reminder);
+                crate::save_config(&config);
+            },
+            None => self.status = "The reminder was changed on disk, reloaded.".to_string(),
+        }
+        self.reload();
+    }
+
+    fn delete(&mut self) {
+        let Some(index) = self.selected() else {
+            return;
+        };
+        let local = &self.sum.reminders[index];
+        let mut config = crate::load_context(&local.path);
+        match config.reminders.iter().position(|v| *v == local.reminder) {
+            Some(position) => {
+                config.reminders.remove(position);
+                crate::save_config(&config);
+                self.status = "Deleted.".to_string();
+            },
+            None => self.status = "The reminder was changed on disk, reloaded.".to_string(),
+        }
+        self.reload();
+    }
+
+    /// Moves the selected reminder to the next or previous priority defined for its context.
+    fn cycle_priority(&mut self, by: isize) {
+        let Some(index) = self.selected() else {
+            return;
+        };
+        let local = &self.sum.reminders[index];
+        let mut names: Vec<String> = vec![];
+        for priority in &self.sum.priorities {
+            let name = &priority.priority.name;
+            if !names.contains(name) && self.sum.resolve_priority(name, &local.path).is_some() {
+                names.push(name.clone());
+            }
+        }
+        if names.is_empty() {
+            return;
+        }
+
+        let current = names.iter().position(|v| *v == local.reminder.priority).map(|v| v as isize).unwrap_or(-1);
+        let next = names[(current + by).rem_euclid(names.len() as isize) as usize].clone();
+        self.update(|v| v.priority = next);
+    }
+}